@@ -0,0 +1,201 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::cell::Cell;
+use std::panic;
+use std::sync::RwLock;
+use std::thread;
+
+use super::{thread_token, SendCell};
+
+static HOOK: RwLock<Option<fn(&AccessEvent)>> = RwLock::new(None);
+
+thread_local! {
+    static IN_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sets a global hook that is called for every access to a `SendCell`.
+///
+/// The hook is called once per accessed `SendCell` with an `AccessEvent` for successful accesses
+/// and for accesses from the wrong thread, before the `SendCell` method panics or returns its
+/// error. Dropping a `SendCell` is not an access and not reported. Accesses to cells from inside
+/// the hook are not reported again.
+///
+/// This is only available with the `access_hook` feature and is meant for enforcing
+/// application-specific policies during testing.
+pub fn set_access_hook(hook: fn(&AccessEvent)) {
+    *HOOK.write().unwrap_or_else(|err| err.into_inner()) = Some(hook);
+}
+
+/// Removes the global hook set with `set_access_hook()`.
+pub fn clear_access_hook() {
+    *HOOK.write().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+pub(crate) fn report<T>(
+    cell: &SendCell<T>,
+    allowed: bool,
+    location: &'static panic::Location<'static>,
+) {
+    let hook = match *HOOK.read().unwrap_or_else(|err| err.into_inner()) {
+        Some(hook) => hook,
+        None => return,
+    };
+
+    if IN_HOOK.with(|in_hook| in_hook.replace(true)) {
+        return;
+    }
+
+    struct Reset;
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            IN_HOOK.with(|in_hook| in_hook.set(false));
+        }
+    }
+
+    let _reset = Reset;
+    hook(&AccessEvent {
+        value: cell.as_ptr() as *const (),
+        origin_thread: cell.thread_id,
+        current_thread: thread_token::current_thread_id(),
+        location,
+        created_at: cell.location,
+        allowed,
+    });
+}
+
+/// An access to a `SendCell` that is reported to the hook set with `set_access_hook()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AccessEvent {
+    value: *const (),
+    origin_thread: thread::ThreadId,
+    current_thread: thread::ThreadId,
+    location: &'static panic::Location<'static>,
+    created_at: &'static panic::Location<'static>,
+    allowed: bool,
+}
+
+impl AccessEvent {
+    /// Returns the address of the wrapped value at the time of the access.
+    ///
+    /// This identifies the `SendCell` as long as it is not moved.
+    pub fn value_ptr(&self) -> *const () {
+        self.value
+    }
+
+    /// Returns the `ThreadId` of the thread the `SendCell` is bound to.
+    pub fn origin_thread(&self) -> thread::ThreadId {
+        self.origin_thread
+    }
+
+    /// Returns the `ThreadId` of the thread that accessed the `SendCell`.
+    pub fn current_thread(&self) -> thread::ThreadId {
+        self.current_thread
+    }
+
+    /// Returns the location of the access.
+    pub fn location(&self) -> &'static panic::Location<'static> {
+        self.location
+    }
+
+    /// Returns the location where the `SendCell` was created.
+    pub fn created_at(&self) -> &'static panic::Location<'static> {
+        self.created_at
+    }
+
+    /// Returns `true` if the access happened from the thread the `SendCell` is bound to.
+    pub fn is_allowed(&self) -> bool {
+        self.allowed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::sync::Arc;
+
+    thread_local! {
+        static EVENTS: RefCell<Vec<AccessEvent>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn record(event: &AccessEvent) {
+        EVENTS.with(|events| events.borrow_mut().push(*event));
+    }
+
+    #[test]
+    fn access_hook() {
+        set_access_hook(record);
+
+        let cell = SendCell::new(1);
+        let ptr = cell.as_ptr() as *const ();
+        let line = line!() + 1;
+        assert_eq!(cell.get(), &1);
+
+        let cell = thread::spawn(move || {
+            assert_eq!(cell.try_get(), None);
+            let events = EVENTS.with(|events| events.take());
+            assert_eq!(events.len(), 1);
+            assert!(!events[0].is_allowed());
+            assert_eq!(events[0].current_thread(), thread::current().id());
+            cell
+        })
+        .join()
+        .unwrap();
+
+        let events = EVENTS.with(|events| events.take());
+        assert_eq!(events.len(), 1);
+        assert!(events[0].is_allowed());
+        assert_eq!(events[0].value_ptr(), ptr);
+        assert_eq!(events[0].location().line(), line);
+        assert_eq!(events[0].created_at(), cell.location());
+    }
+
+    fn count_events<R, F: FnOnce() -> R>(f: F) -> usize {
+        EVENTS.with(|events| events.take());
+        let _ = f();
+        EVENTS.with(|events| events.take()).len()
+    }
+
+    #[test]
+    fn one_event_per_access() {
+        set_access_hook(record);
+
+        assert_eq!(count_events(|| SendCell::new(1).try_into_inner()), 1);
+        assert_eq!(count_events(|| SendCell::new(1).into_inner_expect("")), 1);
+        assert_eq!(count_events(|| SendCell::new(1).try_into_parts()), 1);
+        assert_eq!(count_events(|| SendCell::new(vec![1]).try_scatter()), 1);
+        assert_eq!(
+            count_events(|| SendCell::new(1).try_zip(SendCell::new(2)).is_ok()),
+            2
+        );
+        assert_eq!(
+            count_events(|| SendCell::gather(vec![SendCell::new(1), SendCell::new(2)])),
+            2
+        );
+        assert_eq!(count_events(|| SendCell::new(1) == SendCell::new(1)), 2);
+        assert_eq!(count_events(|| SendCell::new(1) + 1), 1);
+        assert_eq!(count_events(|| SendCell::new(1) + SendCell::new(1)), 2);
+
+        let mut cell = SendCell::new(vec![1]);
+        assert_eq!(count_events(|| cell.extend(Some(2))), 1);
+        assert_eq!(count_events(|| cell.try_update(|v| v)), 1);
+        let cell = Arc::new(cell);
+        assert_eq!(count_events(|| cell.read_with(|v| v.len())), 1);
+
+        let (n, cell) = thread::spawn(move || (count_events(|| cell.try_get()), cell))
+            .join()
+            .unwrap();
+        assert_eq!(n, 1);
+        assert_eq!(count_events(|| drop(cell)), 0);
+
+        // Trait implementations report the location of their caller
+        let (a, b) = (SendCell::new(1), SendCell::new(1));
+        let line = line!() + 1;
+        assert!(a == b);
+        let events = EVENTS.with(|events| events.take());
+        assert!(events.iter().all(|event| event.location().line() == line));
+    }
+}
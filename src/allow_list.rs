@@ -0,0 +1,212 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::mem::{self, ManuallyDrop};
+use std::ops;
+use std::panic;
+
+use super::{
+    default_drop_policy, drop_policy, thread_token, AccessError, SendCellViolation, ThreadToken,
+    ViolationKind,
+};
+
+/// A `SendCell` that is bound to a set of threads instead of exactly one
+///
+/// This is meant for values that are protected by external synchronization, e.g. a handle that
+/// is only used while holding a specific mutex from a small pool of worker threads. Accessing the
+/// value from any of the allowed threads works, while accesses from any other thread panic like
+/// with `SendCell`.
+///
+/// # Warning
+///
+/// Calling `drop` on an `AllowListCell` or otherwise freeing the value from a thread that is not
+/// allowed is handled according to `default_drop_policy()`, which panics by default.
+pub struct AllowListCell<T> {
+    value: ManuallyDrop<T>,
+    threads: Vec<ThreadToken>,
+    location: &'static panic::Location<'static>,
+}
+
+impl<T> AllowListCell<T> {
+    /// Creates a new `AllowListCell` containing `value` that can be accessed from the threads of
+    /// `tokens`.
+    ///
+    /// # Safety
+    ///
+    /// `value` must be safe to be used and dropped on all threads of `tokens`. If `T` is not
+    /// `Sync`, the caller must ensure that the value is never accessed from multiple threads at
+    /// the same time.
+    #[track_caller]
+    pub unsafe fn new<I: IntoIterator<Item = ThreadToken>>(value: T, tokens: I) -> Self {
+        AllowListCell {
+            value: ManuallyDrop::new(value),
+            threads: tokens.into_iter().collect(),
+            location: panic::Location::caller(),
+        }
+    }
+
+    /// Allows accessing the value from the thread of `token` too.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements as for `AllowListCell::new()` apply to the thread of `token`.
+    pub unsafe fn allow(&mut self, token: ThreadToken) {
+        if !self.threads.contains(&token) {
+            self.threads.push(token);
+        }
+    }
+
+    /// Returns `true` if the value can be accessed from the thread of `token`.
+    ///
+    /// This can be called from any thread.
+    pub fn is_allowed(&self, token: &ThreadToken) -> bool {
+        self.threads.contains(token)
+    }
+
+    /// Returns the `ThreadToken`s of all allowed threads.
+    ///
+    /// This can be called from any thread.
+    pub fn allowed_threads(&self) -> &[ThreadToken] {
+        &self.threads
+    }
+
+    fn is_valid(&self) -> bool {
+        self.is_allowed(&ThreadToken::current())
+    }
+
+    // The violation reports the first allowed thread as origin
+    fn violation(&self, kind: ViolationKind) -> SendCellViolation {
+        let origin_thread = self
+            .threads
+            .first()
+            .map_or_else(thread_token::unknown_thread_id, ThreadToken::thread_id);
+        SendCellViolation::from_error(
+            AccessError::for_value(origin_thread, self.location),
+            kind,
+            None,
+        )
+    }
+
+    /// Consumes the `AllowListCell`, returning the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a thread that is not allowed.
+    pub fn into_inner(self) -> T {
+        self.try_into_inner()
+            .unwrap_or_else(|this| panic::panic_any(this.violation(ViolationKind::Access)))
+    }
+
+    /// Consumes the `AllowListCell`, returning the wrapped value if successful.
+    ///
+    /// The wrapped value is returned if this is called from an allowed thread, otherwise the
+    /// `AllowListCell` is returned as `Err(self)`.
+    pub fn try_into_inner(self) -> Result<T, Self> {
+        if !self.is_valid() {
+            return Err(self);
+        }
+
+        let mut this = ManuallyDrop::new(self);
+        drop(mem::take(&mut this.threads));
+        // Safety: `this` is not used anymore afterwards and its Drop impl is not run
+        Ok(unsafe { ManuallyDrop::take(&mut this.value) })
+    }
+
+    /// Immutably borrows the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a thread that is not allowed.
+    pub fn get(&self) -> &T {
+        self.try_get()
+            .unwrap_or_else(|| panic::panic_any(self.violation(ViolationKind::Access)))
+    }
+
+    /// Tries to immutably borrow the wrapped value.
+    ///
+    /// `None` is returned if called from a thread that is not allowed.
+    pub fn try_get(&self) -> Option<&T> {
+        if self.is_valid() {
+            Some(&self.value)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ops::Deref for AllowListCell<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for AllowListCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.try_get() {
+            Some(value) => value.fmt(f),
+            None => write!(f, "AllowListCell(<bound to {:?}>)", self.threads),
+        }
+    }
+}
+
+impl<T> Drop for AllowListCell<T> {
+    fn drop(&mut self) {
+        if mem::needs_drop::<T>() {
+            if self.is_valid() {
+                // Safety: `self.value` is not used anymore afterwards
+                unsafe { ManuallyDrop::drop(&mut self.value) };
+            } else {
+                drop_policy::foreign_drop(
+                    self.violation(ViolationKind::Drop),
+                    default_drop_policy(),
+                );
+            }
+        }
+    }
+}
+
+unsafe impl<T> Send for AllowListCell<T> {}
+unsafe impl<T> Sync for AllowListCell<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn allowed_threads() {
+        let (sender, receiver) = mpsc::channel::<AllowListCell<Cell<i32>>>();
+        let worker = thread::spawn(move || {
+            let cell = receiver.recv().unwrap();
+            cell.get().set(2);
+            cell
+        });
+        let worker_token = ThreadToken::from_thread_id(worker.thread().id());
+
+        let mut cell = unsafe { AllowListCell::new(Cell::new(1), vec![ThreadToken::current()]) };
+        assert!(!cell.is_allowed(&worker_token));
+        unsafe { cell.allow(worker_token) };
+        unsafe { cell.allow(worker_token) };
+        assert_eq!(cell.allowed_threads().len(), 2);
+        assert_eq!(cell.get().get(), 1);
+
+        sender.send(cell).unwrap();
+        let cell = worker.join().unwrap();
+        assert_eq!(cell.get().get(), 2);
+
+        let t = thread::spawn(move || {
+            assert!(cell.try_get().is_none());
+            cell
+        });
+
+        let r = t.join();
+        let cell = r.unwrap();
+        assert_eq!(cell.into_inner().get(), 2);
+    }
+}
@@ -0,0 +1,37 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use super::SendCell;
+
+/// Generates an arbitrary value and wraps it into a new `SendCell` that is bound to the current
+/// thread.
+impl<'a, T: Arbitrary<'a>> Arbitrary<'a> for SendCell<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        T::arbitrary(u).map(SendCell::new)
+    }
+
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        T::arbitrary_take_rest(u).map(SendCell::new)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        T::size_hint(depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arbitrary() {
+        let mut u = Unstructured::new(&[1, 0, 0, 0, 2]);
+        let cell = SendCell::<(u32, u8)>::arbitrary(&mut u).unwrap();
+        assert_eq!(cell.get(), &(1, 2));
+        assert!(cell.thread_token().is_current());
+        assert_eq!(SendCell::<u32>::size_hint(0), u32::size_hint(0));
+    }
+}
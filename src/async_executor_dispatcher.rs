@@ -0,0 +1,185 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_channel::{self, Receiver, Sender};
+use async_executor::LocalExecutor;
+use futures_core::Stream;
+
+use super::{Dispatcher, Job, ThreadToken};
+
+// The task running the queued closures on the thread of the executor
+struct RunJobs(Pin<Box<Receiver<Job>>>);
+
+impl Future for RunJobs {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let this = self.get_mut();
+        while let Poll::Ready(job) = this.0.as_mut().poll_next(cx) {
+            match job {
+                Some(job) => job(),
+                None => return Poll::Ready(()),
+            }
+        }
+        Poll::Pending
+    }
+}
+
+// Drops the closures that were not run yet on the thread of the executor once the task is dropped
+impl Drop for RunJobs {
+    fn drop(&mut self) {
+        self.0.close();
+        while let Ok(job) = self.0.try_recv() {
+            drop(job);
+        }
+    }
+}
+
+/// A `Dispatcher` running closures on an `async_executor::LocalExecutor`
+///
+/// The closures are run by a task spawned on the executor, i.e. on the thread of the executor
+/// whenever it is run or ticked. Once the executor is dropped, closures that were not run yet are
+/// dropped on its thread and no further closures are accepted.
+///
+/// ```
+/// # extern crate async_executor;
+/// # extern crate send_cell;
+///
+/// use async_executor::LocalExecutor;
+/// use send_cell::{LocalExecutorDispatcher, SendCell};
+/// use std::rc::Rc;
+///
+/// let executor = LocalExecutor::new();
+/// let cell = SendCell::builder()
+///     .dispatcher(LocalExecutorDispatcher::new(&executor))
+///     .build(Rc::new(1));
+///
+/// // Dropped on this thread once the executor runs
+/// std::thread::spawn(move || drop(cell)).join().unwrap();
+/// while executor.try_tick() {}
+/// ```
+#[derive(Clone)]
+pub struct LocalExecutorDispatcher {
+    sender: Sender<Job>,
+    token: ThreadToken,
+}
+
+impl LocalExecutorDispatcher {
+    /// Creates a new `LocalExecutorDispatcher` for `executor`, spawning the task that runs the
+    /// closures on it.
+    pub fn new(executor: &LocalExecutor) -> Self {
+        let (sender, receiver) = async_channel::unbounded();
+        executor.spawn(RunJobs(Box::pin(receiver))).detach();
+
+        LocalExecutorDispatcher {
+            sender,
+            token: ThreadToken::current(),
+        }
+    }
+}
+
+impl Dispatcher for LocalExecutorDispatcher {
+    fn thread_token(&self) -> ThreadToken {
+        self.token
+    }
+
+    fn dispatch(&self, job: Job) -> Result<(), Job> {
+        self.sender.try_send(job).map_err(|err| err.into_inner())
+    }
+}
+
+impl fmt::Debug for LocalExecutorDispatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("LocalExecutorDispatcher")
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use {DispatchError, SendCell};
+
+    struct SetOnDrop(Rc<Cell<bool>>, Arc<AtomicBool>);
+
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            self.0.set(true);
+            self.1.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn forward_drop() {
+        let executor = LocalExecutor::new();
+        let local = Rc::new(Cell::new(false));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let cell = SendCell::builder()
+            .dispatcher(LocalExecutorDispatcher::new(&executor))
+            .build(SetOnDrop(local.clone(), dropped.clone()));
+
+        thread::spawn(move || drop(cell)).join().unwrap();
+        assert!(!dropped.load(Ordering::SeqCst));
+        while executor.try_tick() {}
+        assert!(local.get());
+    }
+
+    #[test]
+    fn read_with() {
+        let executor = LocalExecutor::new();
+        let cell = Arc::new(
+            SendCell::builder()
+                .dispatcher(LocalExecutorDispatcher::new(&executor))
+                .build(Rc::new(1)),
+        );
+
+        let t = thread::spawn(move || {
+            let res = cell.read_with(|v| **v + 1);
+            (cell, res)
+        });
+        while !t.is_finished() {
+            if !executor.try_tick() {
+                thread::yield_now();
+            }
+        }
+        let (cell, res) = t.join().unwrap();
+        assert_eq!(res, Ok(2));
+
+        drop(executor);
+        let res = thread::spawn(move || {
+            let res = cell.read_with(|v| **v);
+            std::mem::forget(cell);
+            res
+        })
+        .join()
+        .unwrap();
+        assert_eq!(res, Err(DispatchError::ExecutorDropped));
+    }
+
+    #[test]
+    fn executor_dropped() {
+        let executor = LocalExecutor::new();
+        let local = Rc::new(Cell::new(false));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let cell = SendCell::builder()
+            .dispatcher(LocalExecutorDispatcher::new(&executor))
+            .build(SetOnDrop(local.clone(), dropped.clone()));
+
+        // The queued drop is run on this thread when the executor is dropped
+        thread::spawn(move || drop(cell)).join().unwrap();
+        drop(executor);
+        assert!(local.get());
+    }
+}
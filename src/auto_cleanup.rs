@@ -0,0 +1,276 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::cell::{RefCell, UnsafeCell};
+use std::fmt;
+use std::mem::{self, ManuallyDrop};
+use std::ops;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread;
+
+use super::{
+    default_drop_policy, drop_policy, thread_token, AccessError, SendCellViolation, ThreadToken,
+    ViolationKind,
+};
+
+struct Slot<T> {
+    value: UnsafeCell<ManuallyDrop<T>>,
+    destroyed: AtomicBool,
+}
+
+trait Cleanup {
+    fn destroy(&self);
+}
+
+impl<T> Cleanup for Slot<T> {
+    // Must only be called from the thread where the value was created
+    fn destroy(&self) {
+        if !self.destroyed.swap(true, Ordering::AcqRel) {
+            // Safety: The value is only dropped once and not accessed anymore afterwards
+            unsafe { ManuallyDrop::drop(&mut *self.value.get()) };
+        }
+    }
+}
+
+// Slots of the current thread that are destroyed when the thread exits
+struct Registered(RefCell<Vec<Weak<dyn Cleanup>>>);
+
+impl Drop for Registered {
+    fn drop(&mut self) {
+        for slot in self.0.get_mut().drain(..) {
+            if let Some(slot) = slot.upgrade() {
+                slot.destroy();
+            }
+        }
+    }
+}
+
+thread_local! {
+    static REGISTERED: Registered = const { Registered(RefCell::new(Vec::new())) };
+}
+
+/// A `SendCell` whose value is dropped when its thread exits
+///
+/// The value is registered with its thread when the `AutoCleanupCell` is created. If the thread
+/// exits while the `AutoCleanupCell` is still alive, the value is dropped on that thread and the
+/// `AutoCleanupCell` becomes inert: it can't be accessed anymore and can be dropped from any
+/// thread.
+///
+/// ```
+/// use send_cell::AutoCleanupCell;
+/// use std::rc::Rc;
+///
+/// let cell = std::thread::spawn(|| AutoCleanupCell::new(Rc::new(1)))
+///     .join()
+///     .unwrap();
+///
+/// // The value was dropped when the thread exited
+/// assert!(cell.is_destroyed());
+/// drop(cell);
+/// ```
+///
+/// # Warning
+///
+/// Calling `drop` on an `AutoCleanupCell` or otherwise freeing the value from a different thread
+/// than the one where it was created while that thread is still running is handled according to
+/// `default_drop_policy()`, which panics by default.
+pub struct AutoCleanupCell<T> {
+    slot: Arc<Slot<T>>,
+    thread_id: thread::ThreadId,
+    location: &'static panic::Location<'static>,
+}
+
+impl<T: 'static> AutoCleanupCell<T> {
+    /// Creates a new `AutoCleanupCell` containing `value`.
+    ///
+    /// If the current thread is already exiting, the value is not registered and is not dropped
+    /// automatically.
+    #[track_caller]
+    pub fn new(value: T) -> Self {
+        let slot = Arc::new(Slot {
+            value: UnsafeCell::new(ManuallyDrop::new(value)),
+            destroyed: AtomicBool::new(false),
+        });
+
+        let weak = Arc::downgrade(&slot) as Weak<dyn Cleanup>;
+        let _ = REGISTERED.try_with(|registered| {
+            let mut slots = registered.0.borrow_mut();
+            if slots.len() == slots.capacity() {
+                slots.retain(|slot| slot.strong_count() > 0);
+            }
+            slots.push(weak);
+        });
+
+        AutoCleanupCell {
+            slot,
+            thread_id: thread_token::current_thread_id(),
+            location: panic::Location::caller(),
+        }
+    }
+}
+
+impl<T> AutoCleanupCell<T> {
+    fn is_valid(&self) -> bool {
+        thread_token::current_thread_id() == self.thread_id && !self.is_destroyed()
+    }
+
+    fn violation(&self, kind: ViolationKind) -> SendCellViolation {
+        SendCellViolation::from_error(
+            AccessError::for_value(self.thread_id, self.location),
+            kind,
+            None,
+        )
+    }
+
+    /// Returns `true` if the value was dropped because its thread exited.
+    ///
+    /// This can be called from any thread.
+    pub fn is_destroyed(&self) -> bool {
+        self.slot.destroyed.load(Ordering::Acquire)
+    }
+
+    /// Returns the `ThreadToken` of the thread this `AutoCleanupCell` is bound to.
+    ///
+    /// This can be called from any thread.
+    pub fn thread_token(&self) -> ThreadToken {
+        ThreadToken::from_thread_id(self.thread_id)
+    }
+
+    /// Consumes the `AutoCleanupCell`, returning the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created
+    /// or if the value was already dropped.
+    pub fn into_inner(self) -> T {
+        self.try_into_inner()
+            .unwrap_or_else(|this| panic::panic_any(this.violation(ViolationKind::Access)))
+    }
+
+    /// Consumes the `AutoCleanupCell`, returning the wrapped value if successful.
+    ///
+    /// The wrapped value is returned if this is called from the same thread as the one where the
+    /// original value was created and it was not dropped yet, otherwise the `AutoCleanupCell` is
+    /// returned as `Err(self)`.
+    pub fn try_into_inner(self) -> Result<T, Self> {
+        if !self.is_valid() {
+            return Err(self);
+        }
+
+        self.slot.destroyed.store(true, Ordering::Release);
+        // Safety: The value is marked as destroyed and not accessed or dropped anymore afterwards
+        Ok(unsafe { ManuallyDrop::take(&mut *self.slot.value.get()) })
+    }
+
+    /// Immutably borrows the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created
+    /// or if the value was already dropped.
+    pub fn get(&self) -> &T {
+        self.try_get()
+            .unwrap_or_else(|| panic::panic_any(self.violation(ViolationKind::Access)))
+    }
+
+    /// Tries to immutably borrow the wrapped value.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created or if the value was already dropped.
+    pub fn try_get(&self) -> Option<&T> {
+        if self.is_valid() {
+            // Safety: The value is only dropped on this thread when it exits
+            Some(unsafe { &*self.slot.value.get() })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ops::Deref for AutoCleanupCell<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for AutoCleanupCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.try_get() {
+            Some(value) => value.fmt(f),
+            None if self.is_destroyed() => f.write_str("AutoCleanupCell(<destroyed>)"),
+            None => write!(f, "AutoCleanupCell(<bound to {:?}>)", self.thread_id),
+        }
+    }
+}
+
+impl<T> Drop for AutoCleanupCell<T> {
+    fn drop(&mut self) {
+        if thread_token::current_thread_id() == self.thread_id {
+            self.slot.destroy();
+        } else if mem::needs_drop::<T>() && !self.is_destroyed() {
+            drop_policy::foreign_drop(self.violation(ViolationKind::Drop), default_drop_policy());
+        }
+    }
+}
+
+unsafe impl<T> Send for AutoCleanupCell<T> {}
+unsafe impl<T> Sync for AutoCleanupCell<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::marker::PhantomData;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+
+    struct SetOnDrop(Arc<AtomicBool>, PhantomData<*const ()>);
+
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn cleanup_on_thread_exit() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        let (done_sender, done_receiver) = mpsc::channel::<()>();
+
+        let thread_dropped = dropped.clone();
+        let t = thread::spawn(move || {
+            let cell = AutoCleanupCell::new(SetOnDrop(thread_dropped, PhantomData));
+            assert!(cell.try_get().is_some());
+            sender.send(cell).unwrap();
+            let _ = done_receiver.recv();
+        });
+
+        let cell = receiver.recv().unwrap();
+        assert!(cell.try_get().is_none());
+        assert!(!cell.is_destroyed());
+        drop(done_sender);
+        t.join().unwrap();
+
+        assert!(dropped.load(Ordering::SeqCst));
+        assert!(cell.is_destroyed());
+        assert_ne!(cell.thread_token(), ThreadToken::current());
+        drop(cell);
+    }
+
+    #[test]
+    fn drop_and_into_inner_on_same_thread() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let cell = AutoCleanupCell::new(SetOnDrop(dropped.clone(), PhantomData));
+        drop(cell);
+        assert!(dropped.load(Ordering::SeqCst));
+
+        let cell = AutoCleanupCell::new(Rc::new(1));
+        assert_eq!(**cell, 1);
+        assert_eq!(*cell.into_inner(), 1);
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::cmp;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::panic;
+#[cfg(debug_assertions)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use super::{SendCell, SendCellViolation, ViolationKind};
+
+// The number of outstanding guards of a `SendCell`, only tracked in debug builds
+#[cfg(debug_assertions)]
+pub(crate) type Counter = AtomicUsize;
+#[cfg(not(debug_assertions))]
+pub(crate) type Counter = ();
+
+#[cfg(debug_assertions)]
+pub(crate) fn new_counter() -> Counter {
+    AtomicUsize::new(0)
+}
+
+#[cfg(not(debug_assertions))]
+pub(crate) fn new_counter() -> Counter {}
+
+// Counts a borrow that is not bound to the lifetime of the `SendCell`, e.g. by an `OwnedRef`. It
+// has to be released again with `release()`.
+#[allow(unused_variables)]
+pub(crate) fn acquire<T>(cell: &SendCell<T>) {
+    #[cfg(debug_assertions)]
+    cell.borrows.fetch_add(1, Ordering::Relaxed);
+}
+
+#[allow(unused_variables)]
+pub(crate) fn release<T>(cell: &SendCell<T>) {
+    #[cfg(debug_assertions)]
+    cell.borrows.fetch_sub(1, Ordering::Relaxed);
+}
+
+// Counts a guard borrowing a `SendCell` for as long as it exists. This doesn't affect comparing,
+// ordering or hashing of the guards.
+pub(crate) struct BorrowCount<'a> {
+    #[cfg(debug_assertions)]
+    counter: &'a Counter,
+    phantom: PhantomData<&'a Counter>,
+}
+
+impl<'a> BorrowCount<'a> {
+    pub(crate) fn new<T>(cell: &'a SendCell<T>) -> Self {
+        BorrowCount::from_counter(&cell.borrows)
+    }
+
+    #[allow(unused_variables)]
+    fn from_counter(counter: &'a Counter) -> Self {
+        #[cfg(debug_assertions)]
+        counter.fetch_add(1, Ordering::Relaxed);
+        BorrowCount {
+            #[cfg(debug_assertions)]
+            counter,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a> Clone for BorrowCount<'a> {
+    fn clone(&self) -> Self {
+        #[cfg(debug_assertions)]
+        return BorrowCount::from_counter(self.counter);
+        #[cfg(not(debug_assertions))]
+        return BorrowCount::from_counter(&());
+    }
+}
+
+impl<'a> Drop for BorrowCount<'a> {
+    fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        self.counter.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<'a> PartialEq for BorrowCount<'a> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<'a> Eq for BorrowCount<'a> {}
+
+impl<'a> PartialOrd for BorrowCount<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Ord for BorrowCount<'a> {
+    fn cmp(&self, _other: &Self) -> cmp::Ordering {
+        cmp::Ordering::Equal
+    }
+}
+
+impl<'a> Hash for BorrowCount<'a> {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl<'a> fmt::Debug for BorrowCount<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("BorrowCount")
+    }
+}
+
+impl<T> SendCell<T> {
+    /// Returns the number of `Ref`s, `CheckedRef`s and `OwnedRef`s that currently borrow the
+    /// wrapped value.
+    ///
+    /// This can be called from any thread, e.g. for finding guards that are unexpectedly kept
+    /// alive in long-lived structs. The guards are only counted in debug builds, in release builds
+    /// this always returns 0.
+    ///
+    /// See `SendCellBuilder::check_borrows_on_drop()` for detecting leaked guards.
+    pub fn outstanding_borrows(&self) -> usize {
+        #[cfg(debug_assertions)]
+        return self.borrows.load(Ordering::Relaxed);
+        #[cfg(not(debug_assertions))]
+        return 0;
+    }
+
+    // Panics if guards are still counted when the `SendCell` is dropped, which means that they
+    // were leaked, and this was requested with `SendCellBuilder::check_borrows_on_drop()`
+    pub(crate) fn check_borrows_on_drop(&self) {
+        let check = self
+            .options
+            .as_ref()
+            .is_some_and(|options| options.check_borrows);
+        if check && !thread::panicking() && self.outstanding_borrows() > 0 {
+            panic::panic_any(SendCellViolation::new(
+                self,
+                ViolationKind::OutstandingBorrows,
+                None,
+            ));
+        }
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+    use std::mem;
+    use std::sync::Arc;
+    use {OwnedRef, Ref};
+
+    #[test]
+    fn outstanding_borrows() {
+        let cell = SendCell::new(Box::new(1));
+        assert_eq!(cell.outstanding_borrows(), 0);
+
+        let a = cell.borrow();
+        let b = a.clone();
+        let c = cell.borrow_checked();
+        assert_eq!(cell.outstanding_borrows(), 3);
+
+        let d = Ref::as_deref(b);
+        assert_eq!(*d, 1);
+        assert_eq!(cell.outstanding_borrows(), 3);
+        assert_eq!(a, cell.borrow());
+
+        drop((a, c, d));
+        assert_eq!(cell.outstanding_borrows(), 0);
+
+        let cell = Arc::new(cell);
+        let r = OwnedRef::new(cell.clone());
+        let r2 = r.clone();
+        assert_eq!(cell.outstanding_borrows(), 2);
+        let cell = OwnedRef::into_cell(r);
+        assert_eq!(cell.outstanding_borrows(), 1);
+        drop(r2);
+        assert_eq!(cell.outstanding_borrows(), 0);
+    }
+
+    #[test]
+    fn check_borrows_on_drop() {
+        let cell = SendCell::builder().check_borrows_on_drop().build(1);
+        drop(cell.borrow());
+        drop(cell);
+
+        let cell = SendCell::builder().check_borrows_on_drop().build(1);
+        mem::forget(cell.borrow());
+        let err = panic::catch_unwind(panic::AssertUnwindSafe(|| drop(cell))).unwrap_err();
+        let violation = err.downcast_ref::<SendCellViolation>().unwrap();
+        assert_eq!(violation.kind(), ViolationKind::OutstandingBorrows);
+        assert!(violation
+            .to_string()
+            .ends_with("dropped with outstanding borrows"));
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use super::SendCell;
+
+struct State<T> {
+    output: Option<T>,
+    waker: Option<Waker>,
+    done: bool,
+}
+
+type Shared<T> = Arc<Mutex<State<T>>>;
+
+/// Bridges a future that does not implement `Send` to other threads.
+///
+/// The returned `DriverHandle` is a `Send` future that polls `future` and has to be driven on the
+/// current thread, e.g. by spawning it on a `tokio::task::LocalSet` or a glib `MainContext`. The
+/// returned `BridgedOutput` is a `Send` future that resolves to the output of `future` once it
+/// completed and can be awaited from any thread.
+///
+/// ```
+/// use send_cell::spawn_local_bridged;
+/// use std::future::Future;
+/// use std::pin::Pin;
+/// use std::rc::Rc;
+/// use std::task::{Context, Poll, Waker};
+///
+/// let value = Rc::new(1);
+/// let future = std::future::poll_fn(move |_| Poll::Ready(*value + 1));
+/// let (output, mut driver) = spawn_local_bridged(future);
+///
+/// let mut cx = Context::from_waker(Waker::noop());
+/// assert_eq!(Pin::new(&mut driver).poll(&mut cx), Poll::Ready(()));
+///
+/// let result = std::thread::spawn(move || {
+///     let mut output = Box::pin(output);
+///     let mut cx = Context::from_waker(Waker::noop());
+///     match output.as_mut().poll(&mut cx) {
+///         Poll::Ready(result) => result.unwrap(),
+///         Poll::Pending => unreachable!(),
+///     }
+/// })
+/// .join()
+/// .unwrap();
+/// assert_eq!(result, 2);
+/// ```
+#[track_caller]
+pub fn spawn_local_bridged<F>(future: F) -> (BridgedOutput<F::Output>, DriverHandle<F::Output>)
+where
+    F: Future + 'static,
+    F::Output: Send,
+{
+    let shared = Arc::new(Mutex::new(State {
+        output: None,
+        waker: None,
+        done: false,
+    }));
+
+    (
+        BridgedOutput {
+            shared: shared.clone(),
+        },
+        DriverHandle {
+            future: Some(SendCell::new(Box::pin(future))),
+            shared,
+        },
+    )
+}
+
+/// A future that polls a future that does not implement `Send` on its thread
+///
+/// This is created by `spawn_local_bridged()`.
+///
+/// # Warning
+///
+/// Polling or dropping the `DriverHandle` from a different thread than the one where it was
+/// created results in a panic, same as with `SendCell`.
+pub struct DriverHandle<T> {
+    #[allow(clippy::type_complexity)]
+    future: Option<SendCell<Pin<Box<dyn Future<Output = T>>>>>,
+    shared: Shared<T>,
+}
+
+impl<T> Future for DriverHandle<T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let this = self.get_mut();
+        let cell = match this.future {
+            Some(ref mut cell) => cell,
+            None => return Poll::Ready(()),
+        };
+
+        cell.assert_thread();
+        let output = match cell.value.as_mut().poll(cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => return Poll::Pending,
+        };
+        this.future = None;
+
+        let mut state = this.shared.lock().unwrap();
+        state.output = Some(output);
+        state.done = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+
+        Poll::Ready(())
+    }
+}
+
+impl<T> fmt::Debug for DriverHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("DriverHandle")
+            .field("done", &self.future.is_none())
+            .finish()
+    }
+}
+
+impl<T> Drop for DriverHandle<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock().unwrap_or_else(|err| err.into_inner());
+        state.done = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A future that resolves to the output of a future driven by a `DriverHandle`
+///
+/// This is created by `spawn_local_bridged()`. If the `DriverHandle` is dropped before the future
+/// completed, this resolves to `Err(Canceled)`.
+pub struct BridgedOutput<T> {
+    shared: Shared<T>,
+}
+
+impl<T> Future for BridgedOutput<T> {
+    type Output = Result<T, Canceled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T, Canceled>> {
+        let mut state = self.shared.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(output) = state.output.take() {
+            Poll::Ready(Ok(output))
+        } else if state.done {
+            Poll::Ready(Err(Canceled))
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> fmt::Debug for BridgedOutput<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("BridgedOutput")
+    }
+}
+
+/// An error returned by `BridgedOutput` if its `DriverHandle` was dropped before completion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Canceled;
+
+impl fmt::Display for Canceled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("bridged future was dropped before completion")
+    }
+}
+
+impl error::Error for Canceled {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn bridged_pending() {
+        let ready = Rc::new(Cell::new(false));
+        let future_ready = ready.clone();
+        let (mut output, mut driver) = spawn_local_bridged(std::future::poll_fn(move |_| {
+            if future_ready.get() {
+                Poll::Ready(1)
+            } else {
+                Poll::Pending
+            }
+        }));
+        let mut cx = Context::from_waker(Waker::noop());
+
+        assert_eq!(Pin::new(&mut output).poll(&mut cx), Poll::Pending);
+        assert_eq!(Pin::new(&mut driver).poll(&mut cx), Poll::Pending);
+        ready.set(true);
+        assert_eq!(Pin::new(&mut driver).poll(&mut cx), Poll::Ready(()));
+        assert_eq!(Pin::new(&mut output).poll(&mut cx), Poll::Ready(Ok(1)));
+    }
+
+    #[test]
+    fn bridged_canceled() {
+        let value = Rc::new(1);
+        let (mut output, driver) =
+            spawn_local_bridged(std::future::poll_fn(move |_| Poll::Ready(*value)));
+        drop(driver);
+
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(
+            Pin::new(&mut output).poll(&mut cx),
+            Poll::Ready(Err(Canceled))
+        );
+    }
+}
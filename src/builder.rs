@@ -0,0 +1,379 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::panic;
+use std::sync::Arc;
+
+#[cfg(feature = "cell_stats")]
+use super::stats;
+use super::{orphan, thread_token, Binding, Dispatcher, DropPolicy, SendCell};
+
+// The dispatcher to forward foreign drops to, together with the function doing that for `T`
+type DropForwarder<T> = (Arc<dyn Dispatcher>, fn(T, Binding, &dyn Dispatcher) -> bool);
+
+// Optional configuration of a `SendCell`, only allocated if any option was set
+pub(crate) struct Options<T> {
+    pub(crate) name: Option<&'static str>,
+    pub(crate) drop_policy: Option<DropPolicy>,
+    pub(crate) dispatcher: Option<DropForwarder<T>>,
+    pub(crate) validator: Option<fn(&T) -> bool>,
+    pub(crate) check_borrows: bool,
+}
+
+impl<T> Clone for Options<T> {
+    fn clone(&self) -> Self {
+        Options {
+            name: self.name,
+            drop_policy: self.drop_policy,
+            dispatcher: self.dispatcher.clone(),
+            validator: self.validator,
+            check_borrows: self.check_borrows,
+        }
+    }
+}
+
+// Queues dropping `value` on the thread of `dispatcher`. Returns `false` if the dispatcher can't
+// run closures anymore, in which case the value is leaked and only the original `SendCell` stays
+// counted.
+fn forward_drop<T: 'static>(value: T, binding: Binding, dispatcher: &dyn Dispatcher) -> bool {
+    let cell = SendCell::with_binding(value, binding);
+    match dispatcher.dispatch(Box::new(move || drop(cell))) {
+        Ok(()) => true,
+        Err(job) => {
+            // The value can't be dropped on its thread anymore
+            mem::forget(job);
+            #[cfg(feature = "cell_stats")]
+            stats::remove::<T>(binding.0);
+            orphan::remove(binding.0);
+            false
+        }
+    }
+}
+
+/// A builder for `SendCell`s with additional options
+///
+/// Created with `SendCell::builder()`. Without any options set, `SendCellBuilder::build()` is
+/// equivalent to `SendCell::new()`.
+///
+/// ```
+/// use send_cell::{DropPolicy, SendCell};
+/// use std::rc::Rc;
+///
+/// let cell = SendCell::builder()
+///     .name("bus")
+///     .drop_policy(DropPolicy::Leak)
+///     .build(Rc::new(1));
+///
+/// assert_eq!(cell.name(), Some("bus"));
+/// assert_eq!(**cell.get(), 1);
+/// ```
+pub struct SendCellBuilder<T> {
+    name: Option<&'static str>,
+    drop_policy: Option<DropPolicy>,
+    dispatcher: Option<DropForwarder<T>>,
+    validator: Option<fn(&T) -> bool>,
+    check_borrows: bool,
+    phantom: PhantomData<fn(T)>,
+}
+
+impl<T> SendCellBuilder<T> {
+    fn new() -> Self {
+        SendCellBuilder {
+            name: None,
+            drop_policy: None,
+            dispatcher: None,
+            validator: None,
+            check_borrows: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the name of the `SendCell`.
+    ///
+    /// The name is included in the messages of panics and errors caused by using the `SendCell`
+    /// from the wrong thread.
+    pub fn name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Sets the policy for dropping the `SendCell` on a different thread than the one where it
+    /// was created.
+    ///
+    /// This overrides the policy set with `set_default_drop_policy()` for this `SendCell`.
+    pub fn drop_policy(mut self, policy: DropPolicy) -> Self {
+        self.drop_policy = Some(policy);
+        self
+    }
+
+    /// Sets a predicate that is checked on every access in addition to the thread.
+    ///
+    /// `validator` is only called on the thread the `SendCell` is bound to. If it returns `false`,
+    /// the access is handled the same way as an access from the wrong thread, e.g. for values that
+    /// can only be used while a GL context is current. Dropping the value does not check
+    /// `validator`.
+    ///
+    /// ```
+    /// use send_cell::SendCell;
+    /// use std::cell::Cell;
+    ///
+    /// let cell = SendCell::builder()
+    ///     .validator(|enabled: &Cell<bool>| enabled.get())
+    ///     .build(Cell::new(true));
+    /// assert!(cell.try_get().is_some());
+    ///
+    /// cell.get().set(false);
+    /// assert!(cell.try_get().is_none());
+    /// ```
+    pub fn validator(mut self, validator: fn(&T) -> bool) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    /// Panics if the `SendCell` is dropped while it is still borrowed.
+    ///
+    /// A `SendCell` can only be dropped while borrowed if a `Ref`, `CheckedRef` or `OwnedRef` was
+    /// leaked, e.g. with `mem::forget()`, as counted by `SendCell::outstanding_borrows()`. Like
+    /// the counting this only has an effect in debug builds. The panic payload is a
+    /// `SendCellViolation` of kind `ViolationKind::OutstandingBorrows`.
+    pub fn check_borrows_on_drop(mut self) -> Self {
+        self.check_borrows = true;
+        self
+    }
+
+    /// Creates a new `SendCell` containing `value` with the configured options.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a dispatcher was set that belongs to a different thread.
+    #[track_caller]
+    pub fn build(self, value: T) -> SendCell<T> {
+        let thread_id = thread_token::current_thread_id();
+        if let Some((ref dispatcher, _)) = self.dispatcher {
+            assert!(
+                dispatcher.thread_token().thread_id() == thread_id,
+                "Dispatcher belongs to a different thread"
+            );
+        }
+
+        let mut cell = SendCell::with_binding(value, (thread_id, panic::Location::caller()));
+        if self.name.is_some()
+            || self.drop_policy.is_some()
+            || self.dispatcher.is_some()
+            || self.validator.is_some()
+            || self.check_borrows
+        {
+            cell.options = Some(Box::new(Options {
+                name: self.name,
+                drop_policy: self.drop_policy,
+                dispatcher: self.dispatcher,
+                validator: self.validator,
+                check_borrows: self.check_borrows,
+            }));
+        }
+        cell
+    }
+}
+
+impl<T: 'static> SendCellBuilder<T> {
+    /// Sets the `Dispatcher` to which the value is forwarded if the `SendCell` is dropped on a
+    /// different thread than the one where it was created.
+    ///
+    /// The value is then dropped on the thread of `dispatcher` instead of applying the drop
+    /// policy, e.g. by the `HomeExecutor` of a `HomeHandle`. If the dispatcher can't run closures
+    /// anymore, the drop policy is applied. The `SendCell` has to be built on the thread of the
+    /// dispatcher.
+    pub fn dispatcher<D: Dispatcher>(mut self, dispatcher: D) -> Self {
+        self.dispatcher = Some((Arc::new(dispatcher), forward_drop::<T>));
+        self
+    }
+}
+
+impl<T> fmt::Debug for SendCellBuilder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("SendCellBuilder")
+            .field("name", &self.name)
+            .field("drop_policy", &self.drop_policy)
+            .field(
+                "dispatcher",
+                &self.dispatcher.as_ref().map(|d| d.0.thread_token()),
+            )
+            .field("validator", &self.validator.is_some())
+            .field("check_borrows", &self.check_borrows)
+            .finish()
+    }
+}
+
+impl<T> SendCell<T> {
+    /// Returns a `SendCellBuilder` for creating a `SendCell` with additional options.
+    pub fn builder() -> SendCellBuilder<T> {
+        SendCellBuilder::new()
+    }
+
+    /// Returns the name set with `SendCellBuilder::name()`.
+    ///
+    /// This can be called from any thread.
+    pub fn name(&self) -> Option<&'static str> {
+        self.options.as_ref().and_then(|options| options.name)
+    }
+
+    // Returns the `Dispatcher` set with `SendCellBuilder::dispatcher()`
+    pub(crate) fn dispatcher(&self) -> Option<&Arc<dyn Dispatcher>> {
+        self.options
+            .as_ref()
+            .and_then(|options| options.dispatcher.as_ref())
+            .map(|dispatcher| &dispatcher.0)
+    }
+
+    // Returns a function that creates `SendCell`s for values derived from the one of this
+    // `SendCell`. They are bound to the same thread, have the same creation location and keep the
+    // name, drop policy and borrow check. The validator and dispatcher are specific to `T` and not
+    // kept.
+    pub(crate) fn derive<U>(&self) -> impl Fn(U) -> SendCell<U> {
+        let binding = self.binding();
+        let options = self
+            .options
+            .as_ref()
+            .filter(|options| {
+                options.name.is_some() || options.drop_policy.is_some() || options.check_borrows
+            })
+            .map(|options| {
+                Box::new(Options {
+                    name: options.name,
+                    drop_policy: options.drop_policy,
+                    dispatcher: None,
+                    validator: None,
+                    check_borrows: options.check_borrows,
+                })
+            });
+        move |value| {
+            let mut cell = SendCell::with_binding(value, binding);
+            cell.options = options.clone();
+            cell
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use HomeExecutor;
+
+    #[test]
+    fn build_options() {
+        let cell = SendCell::<i32>::builder().build(1);
+        assert_eq!(cell.name(), None);
+        assert_eq!(cell.get(), &1);
+
+        let cell = SendCell::builder().name("bus").build(Rc::new(2));
+        assert_eq!(cell.name(), Some("bus"));
+        assert_eq!(cell.location().line(), line!() - 2);
+
+        let (cell, err) = thread::spawn(move || {
+            let err = cell.checked_get().unwrap_err();
+            (cell, err)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(err.name(), Some("bus"));
+        assert!(err.to_string().contains("`bus`"));
+        assert_eq!(*cell.into_inner(), 2);
+    }
+
+    #[test]
+    fn validator() {
+        let cell = SendCell::new_with_validator(Rc::new(Cell::new(1)), |v| v.get() > 0);
+        assert_eq!(cell.get().get(), 1);
+
+        cell.get().set(0);
+        assert!(cell.try_get().is_none());
+        let err = cell.checked_get().unwrap_err();
+        assert_eq!(err.origin_thread(), err.current_thread());
+        assert!(err.to_string().contains("failed its validity check"));
+        // Dropping does not check the validator
+    }
+
+    #[test]
+    fn derived_options() {
+        let cell = SendCell::builder()
+            .name("pair")
+            .drop_policy(DropPolicy::Leak)
+            .validator(|v: &(Rc<i32>, Vec<i32>)| *v.0 > 0)
+            .build((Rc::new(1), vec![2, 3]));
+
+        let (a, b) = cell.unzip();
+        assert_eq!(a.name(), Some("pair"));
+        let cells = b.scatter();
+        assert!(cells.iter().all(|cell| cell.name() == Some("pair")));
+        let cell = a.zip(SendCell::new(Some(4)));
+        let (a, b) = cell.unzip();
+        assert_eq!(b.transpose().unwrap().name(), Some("pair"));
+
+        // Leaked instead of panicking
+        thread::spawn(move || drop(a)).join().unwrap();
+    }
+
+    #[test]
+    fn drop_policy_leak() {
+        let cell = SendCell::builder()
+            .drop_policy(DropPolicy::Leak)
+            .build(Rc::new(1));
+
+        thread::spawn(move || drop(cell)).join().unwrap();
+    }
+
+    #[test]
+    fn dispatcher_drop() {
+        struct SetOnDrop(Rc<Cell<bool>>, Arc<AtomicBool>);
+
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.set(true);
+                self.1.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let executor = HomeExecutor::new();
+        let local = Rc::new(Cell::new(false));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let cell = SendCell::builder()
+            .dispatcher(executor.handle())
+            .build(SetOnDrop(local.clone(), dropped.clone()));
+
+        thread::spawn(move || drop(cell)).join().unwrap();
+        assert!(!dropped.load(Ordering::SeqCst));
+
+        assert_eq!(executor.pump(), 1);
+        assert!(local.get());
+    }
+
+    #[test]
+    fn dispatcher_gone() {
+        let executor = HomeExecutor::new();
+        let cell = SendCell::builder()
+            .drop_policy(DropPolicy::Leak)
+            .dispatcher(executor.handle())
+            .build(Rc::new(1));
+        drop(executor);
+
+        thread::spawn(move || drop(cell)).join().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Dispatcher belongs to a different thread")]
+    fn dispatcher_of_other_thread() {
+        let handle = thread::spawn(|| HomeExecutor::new().handle())
+            .join()
+            .unwrap();
+        let _ = SendCell::builder().dispatcher(handle).build(1);
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use crossbeam_channel::{self, Receiver, Sender};
+
+use super::{Dispatcher, Job, ThreadToken};
+
+/// A `Dispatcher` sending closures through a `crossbeam_channel` to a `CrossbeamPump`
+///
+/// Created with `CrossbeamPump::dispatcher()`. The closures are run on the thread of the
+/// `CrossbeamPump` whenever it is pumped, which makes it possible to forward foreign drops and
+/// accesses of `SendCell`s to threads with a crossbeam based event loop.
+///
+/// ```
+/// use send_cell::{CrossbeamPump, SendCell};
+/// use std::rc::Rc;
+///
+/// let pump = CrossbeamPump::new();
+/// let cell = SendCell::builder()
+///     .dispatcher(pump.dispatcher())
+///     .build(Rc::new(1));
+///
+/// // Dropped on this thread once the pump runs
+/// std::thread::spawn(move || drop(cell)).join().unwrap();
+/// assert_eq!(pump.pump(), 1);
+/// ```
+#[derive(Clone)]
+pub struct CrossbeamDispatcher {
+    sender: Sender<Job>,
+    token: ThreadToken,
+}
+
+impl Dispatcher for CrossbeamDispatcher {
+    fn thread_token(&self) -> ThreadToken {
+        self.token
+    }
+
+    fn dispatch(&self, job: Job) -> Result<(), Job> {
+        self.sender.send(job).map_err(|err| err.0)
+    }
+}
+
+impl fmt::Debug for CrossbeamDispatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("CrossbeamDispatcher")
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+/// The receiving side of `CrossbeamDispatcher`s, running their closures on the thread where it was
+/// created
+///
+/// Closures that were not run yet are dropped on this thread together with the `CrossbeamPump`.
+pub struct CrossbeamPump {
+    sender: Sender<Job>,
+    receiver: Receiver<Job>,
+    token: ThreadToken,
+    // Stays on its thread so that queued closures are dropped there
+    _not_send: PhantomData<*const ()>,
+}
+
+impl CrossbeamPump {
+    /// Creates a new `CrossbeamPump` for the current thread.
+    pub fn new() -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        CrossbeamPump {
+            sender,
+            receiver,
+            token: ThreadToken::current(),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns a new `CrossbeamDispatcher` for queueing closures from any thread.
+    pub fn dispatcher(&self) -> CrossbeamDispatcher {
+        CrossbeamDispatcher {
+            sender: self.sender.clone(),
+            token: self.token,
+        }
+    }
+
+    /// Returns the receiver of the queued closures, e.g. for waiting on it with
+    /// `crossbeam_channel::select!` together with other channels.
+    ///
+    /// Received closures have to be run on the thread of the `CrossbeamPump`.
+    pub fn receiver(&self) -> &Receiver<Job> {
+        &self.receiver
+    }
+
+    /// Runs all currently queued closures without blocking and returns how many were run.
+    pub fn pump(&self) -> usize {
+        let mut count = 0;
+        while let Ok(job) = self.receiver.try_recv() {
+            job();
+            count += 1;
+        }
+        count
+    }
+
+    /// Waits up to `timeout` for a closure to be queued, then runs all queued closures and returns
+    /// how many were run.
+    pub fn pump_timeout(&self, timeout: Duration) -> usize {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(job) => {
+                job();
+                1 + self.pump()
+            }
+            Err(_) => 0,
+        }
+    }
+}
+
+impl Default for CrossbeamPump {
+    fn default() -> Self {
+        CrossbeamPump::new()
+    }
+}
+
+impl fmt::Debug for CrossbeamPump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("CrossbeamPump")
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::thread;
+    use {DispatchError, SendCell};
+
+    #[test]
+    fn forward_drop() {
+        struct SetOnDrop(Rc<Cell<bool>>);
+
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let pump = CrossbeamPump::new();
+        let dropped = Rc::new(Cell::new(false));
+        let cell = SendCell::builder()
+            .dispatcher(pump.dispatcher())
+            .build(SetOnDrop(dropped.clone()));
+
+        thread::spawn(move || drop(cell)).join().unwrap();
+        assert!(!dropped.get());
+        assert_eq!(pump.pump(), 1);
+        assert!(dropped.get());
+        assert_eq!(pump.pump(), 0);
+    }
+
+    #[test]
+    fn read_with() {
+        let pump = CrossbeamPump::new();
+        let cell = Arc::new(
+            SendCell::builder()
+                .dispatcher(pump.dispatcher())
+                .build(Rc::new(1)),
+        );
+
+        let t = thread::spawn(move || {
+            let res = cell.read_with(|v| **v + 1);
+            (cell, res)
+        });
+        while !t.is_finished() {
+            pump.pump_timeout(Duration::from_millis(10));
+        }
+        let (cell, res) = t.join().unwrap();
+        assert_eq!(res, Ok(2));
+
+        drop(pump);
+        let res = thread::spawn(move || {
+            let res = cell.read_with(|v| **v);
+            std::mem::forget(cell);
+            res
+        })
+        .join()
+        .unwrap();
+        assert_eq!(res, Err(DispatchError::ExecutorDropped));
+    }
+
+    #[test]
+    fn select() {
+        let pump = CrossbeamPump::new();
+        let dispatcher = pump.dispatcher();
+        let (_sender, receiver) = crossbeam_channel::unbounded::<()>();
+        let counter = SendCell::new(Rc::new(Cell::new(0)));
+        let cloned = counter.clone();
+
+        thread::spawn(move || {
+            dispatcher
+                .dispatch(Box::new(move || cloned.get().set(1)))
+                .ok()
+                .unwrap();
+        })
+        .join()
+        .unwrap();
+
+        crossbeam_channel::select! {
+            recv(pump.receiver()) -> job => job.unwrap()(),
+            recv(receiver) -> _ => unreachable!(),
+        }
+        assert_eq!(counter.get().get(), 1);
+    }
+}
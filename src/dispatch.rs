@@ -0,0 +1,465 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use super::{
+    AccessError, AccessRequest, ExecutorDropped, IntoInnerError, PoisonGuard, SendCell, ThreadToken,
+};
+
+/// A closure queued with a `Dispatcher`
+pub type Job = Box<dyn FnOnce() + Send>;
+
+/// Runs closures on a specific thread
+///
+/// A `SendCell` built with `SendCellBuilder::dispatcher()` forwards its value to its `Dispatcher`
+/// if it is dropped on a different thread, and uses it for accessing the value from other threads,
+/// e.g. in `SendCell::read_with()`. It is implemented by `HomeHandle` and can be implemented for
+/// the event loops of other libraries.
+///
+/// ```
+/// use send_cell::{Dispatcher, Job, SendCell, ThreadToken};
+/// use std::rc::Rc;
+/// use std::sync::{mpsc, Mutex};
+///
+/// struct ChannelDispatcher(Mutex<mpsc::Sender<Job>>, ThreadToken);
+///
+/// impl Dispatcher for ChannelDispatcher {
+///     fn thread_token(&self) -> ThreadToken {
+///         self.1
+///     }
+///
+///     fn dispatch(&self, job: Job) -> Result<(), Job> {
+///         self.0.lock().unwrap().send(job).map_err(|err| err.0)
+///     }
+/// }
+///
+/// let (sender, receiver) = mpsc::channel();
+/// let dispatcher = ChannelDispatcher(Mutex::new(sender), ThreadToken::current());
+/// let cell = SendCell::builder().dispatcher(dispatcher).build(Rc::new(1));
+///
+/// std::thread::spawn(move || drop(cell)).join().unwrap();
+/// receiver.recv().unwrap()();
+/// ```
+pub trait Dispatcher: Send + Sync + 'static {
+    /// Returns the `ThreadToken` of the thread on which closures are run.
+    fn thread_token(&self) -> ThreadToken;
+
+    /// Queues `job` to be run on the thread of the `Dispatcher`.
+    ///
+    /// If closures can't be run on that thread anymore, `job` is returned instead. Queued jobs
+    /// that are never run must be dropped on that thread or leaked, as they can own values bound
+    /// to it.
+    fn dispatch(&self, job: Job) -> Result<(), Job>;
+}
+
+impl<T: 'static> SendCell<T> {
+    /// Calls `f` with the wrapped value on its thread and returns an `AccessRequest` for the
+    /// result.
+    ///
+    /// This can be called from any thread and is the building block of `SendCell::read_with()`
+    /// and the other methods using the dispatcher. If called from the thread the `SendCell` is
+    /// bound to, `f` is run directly. Otherwise it is queued on the dispatcher set with
+    /// `SendCellBuilder::dispatcher()`, and the `AccessRequest` can be awaited, waited on or
+    /// polled for the result. Dropping the `AccessRequest` before `f` was run cancels it.
+    ///
+    /// The queued closure keeps the `SendCell` alive until it was run. The result is an
+    /// `AccessError` if the value can't be accessed on its thread, e.g. because of its validator,
+    /// or if the `SendCell` is bound to a different thread and has no dispatcher.
+    ///
+    /// ```
+    /// use send_cell::{HomeExecutor, SendCell};
+    /// use std::rc::Rc;
+    /// use std::sync::Arc;
+    ///
+    /// let executor = HomeExecutor::new();
+    /// let cell = Arc::new(
+    ///     SendCell::builder()
+    ///         .dispatcher(executor.handle())
+    ///         .build(Rc::new(vec![1, 2, 3])),
+    /// );
+    ///
+    /// let mut request = std::thread::spawn(move || cell.request(|v| v.len()))
+    ///     .join()
+    ///     .unwrap();
+    /// assert!(request.try_wait().is_none());
+    /// executor.pump();
+    /// assert_eq!(request.try_wait(), Some(Ok(Ok(3))));
+    /// ```
+    pub fn request<R, F>(self: &Arc<Self>, f: F) -> AccessRequest<Result<R, AccessError>>
+    where
+        R: Send + 'static,
+        F: FnOnce(&T) -> R + Send + 'static,
+    {
+        fn call<T, R, F: FnOnce(&T) -> R>(cell: &SendCell<T>, f: F) -> Result<R, AccessError> {
+            let value = cell.checked_get()?;
+            let _guard = PoisonGuard::new(&cell.poisoned);
+            Ok(f(value))
+        }
+
+        match self.dispatcher() {
+            Some(dispatcher) if !self.is_on_bound_thread() => {
+                let cell = self.clone();
+                AccessRequest::dispatch(&**dispatcher, move || call(&cell, f))
+            }
+            // Fails with an `AccessError` if called from a different thread
+            _ => AccessRequest::ready(call(self, f)),
+        }
+    }
+
+    // Like `request()` but waits for the result, and fails with `NoDispatcher` instead of an
+    // `AccessError` if called from a different thread without a dispatcher
+    fn request_wait<R, F>(self: &Arc<Self>, f: F) -> Result<R, DispatchError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&T) -> R + Send + 'static,
+    {
+        if self.dispatcher().is_none() && !self.is_on_bound_thread() {
+            return Err(DispatchError::NoDispatcher);
+        }
+
+        self.request(f).wait()?.map_err(DispatchError::Access)
+    }
+
+    /// Calls `f` with the wrapped value on its thread via the dispatcher and returns its result.
+    ///
+    /// This can be called from any thread and blocks until `f` was run, e.g. on worker threads
+    /// that can't await the `AccessRequest` of `SendCell::request()`. If called from a different
+    /// thread, the `SendCell` needs a dispatcher set with `SendCellBuilder::dispatcher()`.
+    ///
+    /// ```
+    /// use send_cell::{HomeExecutor, SendCell};
+    /// use std::rc::Rc;
+    /// use std::sync::Arc;
+    ///
+    /// let executor = HomeExecutor::new();
+    /// let cell = Arc::new(
+    ///     SendCell::builder()
+    ///         .dispatcher(executor.handle())
+    ///         .build(Rc::new(vec![1, 2, 3])),
+    /// );
+    ///
+    /// let t = std::thread::spawn(move || cell.read_with(|v| v.len()));
+    /// while !t.is_finished() {
+    ///     executor.pump_timeout(std::time::Duration::from_millis(10));
+    /// }
+    /// assert_eq!(t.join().unwrap(), Ok(3));
+    /// ```
+    pub fn read_with<R, F>(self: &Arc<Self>, f: F) -> Result<R, DispatchError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&T) -> R + Send + 'static,
+    {
+        self.request_wait(f)
+    }
+
+    /// Clones the wrapped value on its thread via the dispatcher and returns it in a new
+    /// `SendCell`.
+    ///
+    /// This can be called from any thread and blocks until the value was cloned. The new
+    /// `SendCell` is bound to the same thread and has the same options as the original one. If
+    /// called from a different thread, the `SendCell` needs a dispatcher set with
+    /// `SendCellBuilder::dispatcher()`.
+    pub fn clone_via_dispatcher(self: &Arc<Self>) -> Result<SendCell<T>, DispatchError>
+    where
+        T: Clone,
+    {
+        let binding = self.binding();
+        let options = self.options.clone();
+        self.request_wait(move |value| {
+            let mut clone = SendCell::with_binding(value.clone(), binding);
+            clone.options = options;
+            clone
+        })
+    }
+
+    /// Compares the wrapped values of both `SendCell`s on their thread via the dispatcher.
+    ///
+    /// This can be called from any thread and blocks until the values were compared. Both
+    /// `SendCell`s have to be bound to the same thread. If called from a different thread, `self`
+    /// needs a dispatcher set with `SendCellBuilder::dispatcher()`.
+    pub fn eq_via_dispatcher(self: &Arc<Self>, other: &Arc<Self>) -> Result<bool, DispatchError>
+    where
+        T: PartialEq,
+    {
+        let other = other.clone();
+        self.request_wait(move |value| other.checked_get().map(|other| value == other))?
+            .map_err(DispatchError::Access)
+    }
+
+    /// Feeds the wrapped value into `state` on its thread via the dispatcher.
+    ///
+    /// This can be called from any thread and blocks until the value was hashed. A clone of
+    /// `state` is moved to the thread of the value for hashing and then moved back. If called
+    /// from a different thread, the `SendCell` needs a dispatcher set with
+    /// `SendCellBuilder::dispatcher()`. `state` is not modified if an error is returned.
+    pub fn hash_via_dispatcher<H>(self: &Arc<Self>, state: &mut H) -> Result<(), DispatchError>
+    where
+        T: Hash,
+        H: Hasher + Clone + Send + 'static,
+    {
+        let mut hasher = state.clone();
+        *state = self.request_wait(move |value| {
+            value.hash(&mut hasher);
+            hasher
+        })?;
+        Ok(())
+    }
+
+    /// Consumes the `SendCell`, moving it to its thread via `dispatcher` and calling `f` with the
+    /// wrapped value there.
+    ///
+    /// This can be called from any thread. The returned `AccessRequest` can be awaited or waited
+    /// on for the result of `f`. If `dispatcher` belongs to a different thread than the one the
+    /// `SendCell` is bound to, the result is an `IntoInnerError` with the `SendCell`.
+    ///
+    /// ```
+    /// use send_cell::{HomeExecutor, SendCell};
+    /// use std::rc::Rc;
+    ///
+    /// let executor = HomeExecutor::new();
+    /// let handle = executor.handle();
+    /// let cell = SendCell::new(Rc::new(vec![1, 2, 3]));
+    ///
+    /// let t = std::thread::spawn(move || {
+    ///     cell.into_inner_on(&handle, |v| v.iter().sum::<i32>())
+    ///         .wait()
+    ///         .unwrap()
+    ///         .unwrap()
+    /// });
+    /// while !t.is_finished() {
+    ///     executor.pump_timeout(std::time::Duration::from_millis(10));
+    /// }
+    /// assert_eq!(t.join().unwrap(), 6);
+    /// ```
+    pub fn into_inner_on<D, R, F>(
+        self,
+        dispatcher: &D,
+        f: F,
+    ) -> AccessRequest<Result<R, IntoInnerError<T>>>
+    where
+        D: Dispatcher + ?Sized,
+        R: Send + 'static,
+        F: FnOnce(T) -> R + Send + 'static,
+    {
+        AccessRequest::dispatch(dispatcher, move || self.checked_into_inner().map(f))
+    }
+}
+
+/// An error returned when a `SendCell` can't be used on its thread via its dispatcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DispatchError {
+    /// The `SendCell` is bound to a different thread and has no dispatcher.
+    NoDispatcher,
+    /// The dispatcher can't run closures anymore, e.g. because its `HomeExecutor` was dropped.
+    ExecutorDropped,
+    /// The value could not be accessed on its thread, e.g. because of its validator.
+    Access(AccessError),
+}
+
+impl From<ExecutorDropped> for DispatchError {
+    fn from(_: ExecutorDropped) -> Self {
+        DispatchError::ExecutorDropped
+    }
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            DispatchError::NoDispatcher => {
+                f.write_str("value bound to a different thread has no dispatcher")
+            }
+            DispatchError::ExecutorDropped => ExecutorDropped.fmt(f),
+            DispatchError::Access(ref err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for DispatchError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            DispatchError::Access(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+    use std::rc::Rc;
+    use std::thread;
+    use HomeExecutor;
+
+    #[test]
+    fn read_with() {
+        let executor = HomeExecutor::new();
+        let cell = Arc::new(
+            SendCell::builder()
+                .dispatcher(executor.handle())
+                .build(Rc::new(1)),
+        );
+        assert_eq!(cell.read_with(|v| **v + 1), Ok(2));
+
+        let t = thread::spawn(move || {
+            let res = cell.read_with(Rc::strong_count);
+            (cell, res)
+        });
+        while !t.is_finished() {
+            executor.pump_timeout(std::time::Duration::from_millis(10));
+        }
+
+        let (cell, res) = t.join().unwrap();
+        assert_eq!(res, Ok(1));
+        drop(executor);
+        let res = thread::spawn(move || {
+            let res = cell.read_with(|v| **v);
+            mem::forget(cell);
+            res
+        })
+        .join()
+        .unwrap();
+        assert_eq!(res, Err(DispatchError::ExecutorDropped));
+    }
+
+    #[test]
+    fn request() {
+        let executor = HomeExecutor::new();
+        let cell = Arc::new(
+            SendCell::builder()
+                .dispatcher(executor.handle())
+                .validator(|v: &Rc<i32>| **v > 0)
+                .build(Rc::new(1)),
+        );
+        let mut request = cell.request(|v| **v);
+        assert_eq!(request.try_wait(), Some(Ok(Ok(1))));
+
+        let cloned = cell.clone();
+        let (mut request, canceled) = thread::spawn(move || {
+            let request = cloned.request(|v| **v + 1);
+            drop(cloned.request(|_| unreachable!()));
+            (request, cloned)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(request.try_wait(), None);
+        assert_eq!(executor.pump(), 2);
+        assert_eq!(request.try_wait(), Some(Ok(Ok(2))));
+        // The queued closures don't keep the cell alive after they were run
+        assert_eq!(Arc::strong_count(&canceled), 2);
+
+        let cell = Arc::new(SendCell::builder().validator(|v: &i32| *v > 0).build(0));
+        let err = cell.request(|v| *v).wait().unwrap().unwrap_err();
+        assert!(err.to_string().contains("failed its validity check"));
+    }
+
+    #[test]
+    fn clone_via_dispatcher() {
+        let executor = HomeExecutor::new();
+        let cell = Arc::new(
+            SendCell::builder()
+                .name("counter")
+                .dispatcher(executor.handle())
+                .build(Rc::new(1)),
+        );
+
+        let local = cell.clone_via_dispatcher().unwrap();
+        assert_eq!(**local.get(), 1);
+
+        let t = thread::spawn(move || {
+            let clone = cell.clone_via_dispatcher();
+            (cell, clone)
+        });
+        while !t.is_finished() {
+            executor.pump_timeout(std::time::Duration::from_millis(10));
+        }
+
+        let (cell, clone) = t.join().unwrap();
+        let clone = clone.unwrap();
+        assert!(clone.same_thread(&cell));
+        assert_eq!(clone.name(), Some("counter"));
+        assert_eq!(Rc::strong_count(clone.get()), 3);
+    }
+
+    #[test]
+    fn eq_and_hash_via_dispatcher() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let executor = HomeExecutor::new();
+        let handle = executor.handle();
+        let a = Arc::new(
+            SendCell::builder()
+                .dispatcher(handle.clone())
+                .build(Rc::new(1)),
+        );
+        let b = Arc::new(SendCell::builder().dispatcher(handle).build(Rc::new(1)));
+
+        let mut local = DefaultHasher::new();
+        Rc::new(1).hash(&mut local);
+        let local = local.finish();
+
+        let t = thread::spawn(move || {
+            let eq = a.eq_via_dispatcher(&b);
+            let mut hasher = DefaultHasher::new();
+            let hashed = a.hash_via_dispatcher(&mut hasher);
+            (a, b, eq, hashed.map(|_| hasher.finish()))
+        });
+        while !t.is_finished() {
+            executor.pump_timeout(std::time::Duration::from_millis(10));
+        }
+
+        let (a, _b, eq, hash) = t.join().unwrap();
+        assert_eq!(eq, Ok(true));
+        assert_eq!(hash, Ok(local));
+        assert_eq!(
+            a.eq_via_dispatcher(&Arc::new(SendCell::new(Rc::new(2)))),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn into_inner_on() {
+        let executor = HomeExecutor::new();
+        let handle = executor.handle();
+        let cell = SendCell::new(Rc::new(1));
+
+        let other_handle = handle.clone();
+        let mut request = thread::spawn(move || cell.into_inner_on(&other_handle, |v| *v + 1))
+            .join()
+            .unwrap();
+        assert_eq!(request.try_wait().map(|_| ()), None);
+        assert_eq!(executor.pump(), 1);
+        assert_eq!(request.try_wait().unwrap().unwrap().ok(), Some(2));
+
+        let cell = thread::spawn(move || SendCell::new(Rc::new(2)))
+            .join()
+            .unwrap();
+        let err = cell
+            .into_inner_on(&handle, |v| *v)
+            .wait()
+            .unwrap()
+            .unwrap_err();
+        // Forget so drop() is not run, which would panic
+        mem::forget(err.into_cell());
+    }
+
+    #[test]
+    fn clone_without_dispatcher() {
+        let cell = thread::spawn(move || Arc::new(SendCell::new(Rc::new(1))))
+            .join()
+            .unwrap();
+        assert_eq!(
+            cell.clone_via_dispatcher().err(),
+            Some(DispatchError::NoDispatcher)
+        );
+        let err = cell.request(|v| **v).wait().unwrap().unwrap_err();
+        assert_eq!(err.origin_thread(), cell.thread_token().thread_id());
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::oneshot;
+use super::{ExecutorDropped, SendCell};
+
+// Drops the `SendCell` on its thread and then signals completion, also if the `HomeExecutor` is
+// dropped without running the job
+struct DisposeJob<T> {
+    cell: Option<SendCell<T>>,
+    done: Option<oneshot::Sender<()>>,
+}
+
+impl<T> Drop for DisposeJob<T> {
+    fn drop(&mut self) {
+        drop(self.cell.take());
+        if let Some(done) = self.done.take() {
+            done.send(());
+        }
+    }
+}
+
+impl<T: 'static> SendCell<T> {
+    /// Consumes the `SendCell`, dropping the wrapped value on the thread where it was created.
+    ///
+    /// The returned `Dispose` future resolves once the value was actually dropped. If called from
+    /// the same thread as the one where the original value was created, the value is dropped
+    /// immediately. Otherwise the value is forwarded to the `Dispatcher` configured with
+    /// `SendCellBuilder::dispatcher()`, or the `SendCell` is returned as `Err(self)` if there is
+    /// none.
+    ///
+    /// If the dispatcher can't run closures anymore, e.g. because its `HomeExecutor` was dropped,
+    /// the value is leaked and the future resolves to `Err(ExecutorDropped)`.
+    pub fn dispose(self) -> Result<Dispose, Self> {
+        if self.is_on_bound_thread() {
+            drop(self);
+            return Ok(Dispose {
+                state: DisposeState::Done(Ok(())),
+            });
+        }
+
+        let dispatcher = match self.dispatcher() {
+            Some(dispatcher) => dispatcher.clone(),
+            None => return Err(self),
+        };
+
+        let (done, receiver) = oneshot::channel();
+        let job = DisposeJob {
+            cell: Some(self),
+            done: Some(done),
+        };
+        let state = match dispatcher.dispatch(Box::new(move || drop(job))) {
+            Ok(()) => DisposeState::Pending(receiver),
+            Err(job) => {
+                // The value can't be dropped on its thread anymore
+                mem::forget(job);
+                DisposeState::Done(Err(ExecutorDropped))
+            }
+        };
+
+        Ok(Dispose { state })
+    }
+}
+
+/// A future that resolves once the value of a `SendCell` was dropped on its thread
+///
+/// This is created by `SendCell::dispose()` and can also be waited on synchronously with
+/// `Dispose::wait()`.
+pub struct Dispose {
+    state: DisposeState,
+}
+
+enum DisposeState {
+    Pending(oneshot::Receiver<()>),
+    Done(Result<(), ExecutorDropped>),
+    Finished,
+}
+
+impl Dispose {
+    /// Blocks the current thread until the value was dropped.
+    ///
+    /// This must not be called from the thread of the `HomeExecutor` as it would never be pumped.
+    pub fn wait(self) -> Result<(), ExecutorDropped> {
+        match self.state {
+            DisposeState::Pending(receiver) => receiver.recv().ok_or(ExecutorDropped),
+            DisposeState::Done(res) => res,
+            DisposeState::Finished => panic!("`Dispose` polled after completion"),
+        }
+    }
+}
+
+impl Future for Dispose {
+    type Output = Result<(), ExecutorDropped>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), ExecutorDropped>> {
+        let this = self.get_mut();
+        let res = match this.state {
+            DisposeState::Pending(ref mut receiver) => match receiver.poll_recv(cx) {
+                Poll::Ready(res) => res.ok_or(ExecutorDropped),
+                Poll::Pending => return Poll::Pending,
+            },
+            DisposeState::Done(res) => res,
+            DisposeState::Finished => panic!("`Dispose` polled after completion"),
+        };
+        this.state = DisposeState::Finished;
+
+        Poll::Ready(res)
+    }
+}
+
+impl fmt::Debug for Dispose {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("Dispose")
+            .field("done", &!matches!(self.state, DisposeState::Pending(_)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::task::Waker;
+    use std::thread;
+    use HomeExecutor;
+
+    struct SetOnDrop(Rc<Cell<bool>>, Arc<AtomicBool>);
+
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            self.0.set(true);
+            self.1.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn dispose_on_home_thread() {
+        let mut dispose = SendCell::new(Rc::new(1)).dispose().unwrap();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(Pin::new(&mut dispose).poll(&mut cx), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    fn dispose_poisoned() {
+        let local = Rc::new(Cell::new(false));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let cell = SendCell::new(SetOnDrop(local.clone(), dropped));
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            cell.inspect(|_| panic!("half-updated"));
+        }));
+        assert!(res.is_err());
+        assert!(cell.is_poisoned());
+
+        // Dropped directly like in `drop()`, without needing a dispatcher
+        let mut dispose = cell.dispose().ok().unwrap();
+        assert!(local.get());
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(Pin::new(&mut dispose).poll(&mut cx), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    fn dispose_via_dispatcher() {
+        let executor = HomeExecutor::new();
+        let local = Rc::new(Cell::new(false));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let cell = SendCell::builder()
+            .dispatcher(executor.handle())
+            .build(SetOnDrop(local.clone(), dropped.clone()));
+
+        let mut dispose = thread::spawn(move || cell.dispose().ok().unwrap())
+            .join()
+            .unwrap();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(Pin::new(&mut dispose).poll(&mut cx), Poll::Pending);
+        assert!(!local.get());
+
+        assert_eq!(executor.pump(), 1);
+        assert!(local.get());
+        assert_eq!(Pin::new(&mut dispose).poll(&mut cx), Poll::Ready(Ok(())));
+
+        let cell = SendCell::builder()
+            .dispatcher(executor.handle())
+            .build(SetOnDrop(local.clone(), dropped.clone()));
+        dropped.store(false, Ordering::SeqCst);
+        let t = thread::spawn(move || {
+            let res = cell.dispose().ok().unwrap().wait();
+            (res, dropped.load(Ordering::SeqCst))
+        });
+        while !t.is_finished() {
+            executor.pump_timeout(std::time::Duration::from_millis(10));
+        }
+
+        assert_eq!(t.join().unwrap(), (Ok(()), true));
+    }
+
+    #[test]
+    fn dispose_without_dispatcher() {
+        let cell = thread::spawn(move || SendCell::new(1)).join().unwrap();
+        let cell = cell.dispose().unwrap_err();
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+
+    #[test]
+    fn dispose_executor_dropped() {
+        let executor = HomeExecutor::new();
+        let handle = executor.handle();
+        let cell = SendCell::builder().dispatcher(handle).build(Rc::new(1));
+        drop(executor);
+
+        let res = thread::spawn(move || cell.dispose().unwrap().wait())
+            .join()
+            .unwrap();
+        assert_eq!(res, Err(ExecutorDropped));
+    }
+}
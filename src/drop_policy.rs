@@ -0,0 +1,197 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::mem;
+use std::panic;
+use std::process;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{self, Mutex};
+use std::thread;
+
+use super::{thread_token, SendCellViolation};
+
+static DEFAULT_POLICY: AtomicU8 = AtomicU8::new(DropPolicy::Panic as u8);
+
+static RECORDED_DROPS: Mutex<Vec<SendCellViolation>> = Mutex::new(Vec::new());
+
+/// What happens when a `SendCell` is dropped on a different thread than the one where the
+/// original value was created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DropPolicy {
+    /// Panic with a `SendCellViolation`. This is the default.
+    ///
+    /// If the thread is already panicking, the value is leaked and the violation is printed to
+    /// stderr instead as a second panic would abort the process.
+    #[default]
+    Panic,
+    /// Leak the value and print the violation to stderr.
+    Leak,
+    /// Print the violation to stderr and abort the process.
+    Abort,
+    /// Leak the value, print the violation to stderr and record it for
+    /// `take_recorded_drops()`.
+    ///
+    /// This is meant for tests, which can check at their end with `assert_no_recorded_drops()`
+    /// that no `SendCell` was dropped on the wrong thread instead of having to avoid the panic.
+    Record,
+}
+
+/// Sets the policy for `SendCell`s that are dropped on a different thread than the one where the
+/// original value was created.
+///
+/// This affects all `SendCell`s of the process and is meant to be called once at startup, e.g.
+/// for selecting a stricter policy during testing than in production.
+///
+/// ```
+/// use send_cell::{DropPolicy, SendCell};
+/// use std::rc::Rc;
+///
+/// send_cell::set_default_drop_policy(DropPolicy::Leak);
+///
+/// let cell = SendCell::new(Rc::new(1));
+/// std::thread::spawn(move || {
+///     // Leaks the value instead of panicking
+///     drop(cell);
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+pub fn set_default_drop_policy(policy: DropPolicy) {
+    DEFAULT_POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+/// Returns the policy set with `set_default_drop_policy()`.
+pub fn default_drop_policy() -> DropPolicy {
+    match DEFAULT_POLICY.load(Ordering::Relaxed) {
+        x if x == DropPolicy::Leak as u8 => DropPolicy::Leak,
+        x if x == DropPolicy::Abort as u8 => DropPolicy::Abort,
+        x if x == DropPolicy::Record as u8 => DropPolicy::Record,
+        _ => DropPolicy::Panic,
+    }
+}
+
+// Called by `SendCell::drop()` and the drop implementations of the other thread-bound types on the
+// wrong thread. Only returns if the value is to be leaked.
+pub(crate) fn foreign_drop(violation: SendCellViolation, policy: DropPolicy) {
+    // Panicking again would abort the process and hide the original panic
+    if thread::panicking() {
+        eprintln!("send-cell: leaking value while panicking: {}", violation);
+        return;
+    }
+
+    match policy {
+        DropPolicy::Panic => panic::panic_any(violation),
+        DropPolicy::Leak => eprintln!("send-cell: leaking value: {}", violation),
+        DropPolicy::Abort => {
+            eprintln!("send-cell: aborting: {}", violation);
+            process::abort();
+        }
+        DropPolicy::Record => {
+            eprintln!("send-cell: leaking value: {}", violation);
+            recorded_drops().push(violation);
+        }
+    }
+}
+
+fn recorded_drops() -> sync::MutexGuard<'static, Vec<SendCellViolation>> {
+    RECORDED_DROPS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Returns the violations recorded by `DropPolicy::Record` for `SendCell`s bound to the current
+/// thread and removes them.
+///
+/// Only violations of cells bound to the current thread are returned so that tests running in
+/// parallel don't see each other's violations.
+///
+/// ```
+/// use send_cell::{DropPolicy, SendCell};
+/// use std::rc::Rc;
+///
+/// let cell = SendCell::builder()
+///     .drop_policy(DropPolicy::Record)
+///     .build(Rc::new(1));
+/// std::thread::spawn(move || drop(cell)).join().unwrap();
+///
+/// assert_eq!(send_cell::take_recorded_drops().len(), 1);
+/// send_cell::assert_no_recorded_drops();
+/// ```
+pub fn take_recorded_drops() -> Vec<SendCellViolation> {
+    let current = thread_token::current_thread_id();
+    let mut recorded = recorded_drops();
+    let (taken, kept) = mem::take(&mut *recorded)
+        .into_iter()
+        .partition(|violation| violation.origin_thread() == current);
+    *recorded = kept;
+    taken
+}
+
+/// Panics if `DropPolicy::Record` recorded any violations for `SendCell`s bound to the current
+/// thread.
+///
+/// The violations are removed, same as with `take_recorded_drops()`.
+#[track_caller]
+pub fn assert_no_recorded_drops() {
+    let recorded = take_recorded_drops();
+    if !recorded.is_empty() {
+        let messages = recorded
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect::<Vec<_>>();
+        panic!(
+            "{} SendCell(s) dropped on the wrong thread:\n{}",
+            recorded.len(),
+            messages.join("\n")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy() {
+        assert_eq!(DropPolicy::default(), DropPolicy::Panic);
+        assert_eq!(default_drop_policy(), DropPolicy::Panic);
+    }
+
+    #[test]
+    fn record() {
+        use std::rc::Rc;
+        use SendCell;
+
+        let cell = SendCell::builder()
+            .drop_policy(DropPolicy::Record)
+            .build(Rc::new(1));
+        let other = thread::spawn(|| {
+            SendCell::builder()
+                .drop_policy(DropPolicy::Record)
+                .build(Rc::new(2))
+        })
+        .join()
+        .unwrap();
+        thread::spawn(move || drop(cell)).join().unwrap();
+        drop(other);
+
+        let recorded = take_recorded_drops();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].origin_thread(), thread::current().id());
+        assert!(take_recorded_drops().is_empty());
+        assert_no_recorded_drops();
+    }
+
+    #[test]
+    #[should_panic(expected = "1 SendCell(s) dropped on the wrong thread")]
+    fn assert_recorded() {
+        use std::rc::Rc;
+        use SendCell;
+
+        let cell = SendCell::builder()
+            .drop_policy(DropPolicy::Record)
+            .build(Rc::new(1));
+        thread::spawn(move || drop(cell)).join().unwrap();
+
+        assert_no_recorded_drops();
+    }
+}
@@ -0,0 +1,417 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::error;
+use std::fmt;
+use std::panic;
+use std::thread;
+
+use super::{thread_token, SendCell};
+
+/// An error returned when a `SendCell` is accessed from a different thread than the one where
+/// the original value was created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AccessError {
+    origin_thread: thread::ThreadId,
+    current_thread: thread::ThreadId,
+    location: &'static panic::Location<'static>,
+    name: Option<&'static str>,
+    poisoned: bool,
+}
+
+impl AccessError {
+    pub(crate) fn new<T>(cell: &SendCell<T>) -> Self {
+        AccessError {
+            name: cell.name(),
+            poisoned: cell.is_poisoned(),
+            ..AccessError::for_value(cell.bound_thread_id(), cell.location)
+        }
+    }
+
+    // Creates an error for a value outside of a `SendCell` that is bound to `origin_thread` and
+    // was created at `location`
+    pub(crate) fn for_value(
+        origin_thread: thread::ThreadId,
+        location: &'static panic::Location<'static>,
+    ) -> Self {
+        AccessError {
+            origin_thread,
+            current_thread: thread_token::current_thread_id(),
+            location,
+            name: None,
+            poisoned: false,
+        }
+    }
+
+    /// Returns the `ThreadId` of the thread the `SendCell` is bound to.
+    pub fn origin_thread(&self) -> thread::ThreadId {
+        self.origin_thread
+    }
+
+    /// Returns the `ThreadId` of the thread that tried to access the `SendCell`.
+    pub fn current_thread(&self) -> thread::ThreadId {
+        self.current_thread
+    }
+
+    /// Returns the location where the `SendCell` was created.
+    pub fn location(&self) -> &'static panic::Location<'static> {
+        self.location
+    }
+
+    /// Returns the name of the `SendCell` if it was set with `SendCellBuilder::name()`.
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /// Returns `true` if the `SendCell` was poisoned by a panic during an earlier access.
+    ///
+    /// See `SendCell::is_poisoned()`.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("value ")?;
+        if let Some(name) = self.name {
+            write!(f, "`{}` ", name)?;
+        }
+        if self.origin_thread == self.current_thread && self.poisoned {
+            write!(
+                f,
+                "created at {} on thread {:?} is poisoned by a panic during an earlier access",
+                self.location, self.origin_thread
+            )
+        } else if self.origin_thread == self.current_thread {
+            write!(
+                f,
+                "created at {} on thread {:?} failed its validity check",
+                self.location, self.origin_thread
+            )
+        } else {
+            write!(
+                f,
+                "created at {} on thread {:?} accessed from thread {:?}",
+                self.location, self.origin_thread, self.current_thread
+            )
+        }
+    }
+}
+
+impl error::Error for AccessError {}
+
+/// The payload of panics caused by using a `SendCell` from the wrong thread
+///
+/// `SendCell` panics with this type via `std::panic::panic_any()`, which allows panic hooks and
+/// callers of `std::panic::catch_unwind()` to downcast the payload and inspect the violation.
+///
+/// ```
+/// use send_cell::{SendCell, SendCellViolation, ViolationKind};
+/// use std::panic;
+///
+/// let cell = std::thread::spawn(|| SendCell::new(1)).join().unwrap();
+/// let err = panic::catch_unwind(|| {
+///     cell.get();
+/// })
+/// .unwrap_err();
+///
+/// let violation = err.downcast_ref::<SendCellViolation>().unwrap();
+/// assert_eq!(violation.kind(), ViolationKind::Access);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SendCellViolation {
+    kind: ViolationKind,
+    error: AccessError,
+    message: Option<String>,
+    description: Option<&'static str>,
+}
+
+/// The kind of a `SendCellViolation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ViolationKind {
+    /// The wrapped value was accessed from the wrong thread.
+    Access,
+    /// The `SendCell` was dropped on the wrong thread.
+    Drop,
+    /// The wrapped value was accessed on its thread after a panic during an earlier access
+    /// poisoned the `SendCell`.
+    ///
+    /// See `SendCell::is_poisoned()`.
+    Poisoned,
+    /// The `SendCell` was dropped while it was still borrowed by leaked guards.
+    ///
+    /// See `SendCellBuilder::check_borrows_on_drop()`.
+    OutstandingBorrows,
+}
+
+impl SendCellViolation {
+    // Accesses of poisoned cells on their thread are reported as `ViolationKind::Poisoned`
+    pub(crate) fn new<T>(cell: &SendCell<T>, kind: ViolationKind, message: Option<&str>) -> Self {
+        let error = AccessError::new(cell);
+        let kind = match kind {
+            ViolationKind::Access
+                if error.poisoned && error.origin_thread == error.current_thread =>
+            {
+                ViolationKind::Poisoned
+            }
+            kind => kind,
+        };
+        SendCellViolation::from_error(error, kind, message)
+    }
+
+    pub(crate) fn from_error(
+        error: AccessError,
+        kind: ViolationKind,
+        message: Option<&str>,
+    ) -> Self {
+        SendCellViolation {
+            kind,
+            error,
+            message: message.map(String::from),
+            description: None,
+        }
+    }
+
+    // Replaces the description of accesses from the wrong thread in the `Display` implementation,
+    // for values that are not wrapped in a fragile container
+    pub(crate) fn with_description(mut self, description: &'static str) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    /// Returns the kind of the violation.
+    pub fn kind(&self) -> ViolationKind {
+        self.kind
+    }
+
+    /// Returns the `AccessError` with the origin and current thread of the violation.
+    pub fn error(&self) -> &AccessError {
+        &self.error
+    }
+
+    /// Returns the `ThreadId` of the thread the `SendCell` is bound to.
+    pub fn origin_thread(&self) -> thread::ThreadId {
+        self.error.origin_thread
+    }
+
+    /// Returns the `ThreadId` of the thread that used the `SendCell`.
+    pub fn current_thread(&self) -> thread::ThreadId {
+        self.error.current_thread
+    }
+
+    /// Returns the location where the `SendCell` was created.
+    pub fn location(&self) -> &'static panic::Location<'static> {
+        self.error.location
+    }
+
+    /// Returns the custom message passed to one of the `expect`-style accessors.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+impl fmt::Display for SendCellViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if let Some(ref message) = self.message {
+            write!(f, "{}: ", message)?;
+        }
+
+        match self.kind {
+            ViolationKind::Access if self.error.origin_thread == self.error.current_thread => {
+                self.error.fmt(f)
+            }
+            ViolationKind::Access => f.write_str(self.description.unwrap_or(
+                "trying to access wrapped value in fragile container from incorrect thread.",
+            )),
+            ViolationKind::Drop => f.write_str("destructor of fragile object ran on wrong thread"),
+            ViolationKind::Poisoned => self.error.fmt(f),
+            ViolationKind::OutstandingBorrows => write!(
+                f,
+                "SendCell created at {} dropped with outstanding borrows",
+                self.error.location
+            ),
+        }
+    }
+}
+
+impl error::Error for SendCellViolation {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// An error returned by `SendCell::checked_into_inner()`
+///
+/// This carries the `SendCell` so it is not lost, together with the `AccessError` describing why
+/// it could not be consumed.
+pub struct IntoInnerError<T> {
+    cell: SendCell<T>,
+    error: AccessError,
+}
+
+impl<T> IntoInnerError<T> {
+    pub(crate) fn new(cell: SendCell<T>) -> Self {
+        IntoInnerError {
+            error: AccessError::new(&cell),
+            cell,
+        }
+    }
+
+    /// Returns the `AccessError` describing why the `SendCell` could not be consumed.
+    pub fn error(&self) -> &AccessError {
+        &self.error
+    }
+
+    /// Returns the `ThreadId` of the thread the `SendCell` is bound to.
+    pub fn origin_thread(&self) -> thread::ThreadId {
+        self.error.origin_thread
+    }
+
+    /// Returns the `ThreadId` of the thread that tried to consume the `SendCell`.
+    pub fn current_thread(&self) -> thread::ThreadId {
+        self.error.current_thread
+    }
+
+    /// Returns the location where the `SendCell` was created.
+    pub fn location(&self) -> &'static panic::Location<'static> {
+        self.error.location
+    }
+
+    /// Consumes the error, returning the `SendCell`.
+    pub fn into_cell(self) -> SendCell<T> {
+        self.cell
+    }
+}
+
+impl<T> fmt::Debug for IntoInnerError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_tuple("IntoInnerError").field(&self.error).finish()
+    }
+}
+
+impl<T> fmt::Display for IntoInnerError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.error.fmt(f)
+    }
+}
+
+impl<T> error::Error for IntoInnerError<T> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// An error returned by `SendCell::gather()`
+///
+/// This carries all cells passed to `SendCell::gather()` so they are not lost, together with the
+/// index of the first cell that is bound to a different thread.
+pub struct GatherError<T> {
+    cells: Vec<SendCell<T>>,
+    index: usize,
+    error: AccessError,
+}
+
+impl<T> GatherError<T> {
+    pub(crate) fn new(cells: Vec<SendCell<T>>, index: usize) -> Self {
+        GatherError {
+            error: AccessError::new(&cells[index]),
+            cells,
+            index,
+        }
+    }
+
+    /// Returns the index of the first cell that is bound to a different thread.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the `AccessError` for the cell at `GatherError::index()`.
+    pub fn error(&self) -> &AccessError {
+        &self.error
+    }
+
+    /// Returns the cells that were passed to `SendCell::gather()`.
+    pub fn cells(&self) -> &[SendCell<T>] {
+        &self.cells
+    }
+
+    /// Consumes the error, returning the cells that were passed to `SendCell::gather()`.
+    pub fn into_cells(self) -> Vec<SendCell<T>> {
+        self.cells
+    }
+}
+
+impl<T> fmt::Debug for GatherError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("GatherError")
+            .field("index", &self.index)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for GatherError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "cell {}: {}", self.index, self.error)
+    }
+}
+
+impl<T> error::Error for GatherError<T> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_get() {
+        let cell = SendCell::new(1);
+        assert_eq!(cell.checked_get(), Ok(&1));
+
+        let t = thread::spawn(move || {
+            let err = cell.checked_get().unwrap_err();
+            assert_eq!(err.current_thread(), thread::current().id());
+            assert_eq!(err.origin_thread(), cell.thread_token().thread_id());
+            assert_eq!(err.location(), cell.location());
+            cell
+        });
+
+        let r = t.join();
+        assert!(r.is_ok());
+    }
+
+    #[test]
+    fn checked_into_inner_failure() {
+        let (cell, location) = thread::spawn(move || {
+            let cell = SendCell::new(1);
+            let location = cell.location();
+            (cell, location)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(location.file(), file!());
+
+        let err = cell.checked_into_inner().unwrap_err();
+        assert_eq!(
+            err.origin_thread(),
+            err.into_cell().thread_token().thread_id()
+        );
+
+        let cell = SendCell::new(1);
+        let t = thread::spawn(move || {
+            let err = cell.checked_into_inner().unwrap_err();
+            assert_eq!(err.current_thread(), thread::current().id());
+            assert_ne!(err.origin_thread(), thread::current().id());
+            assert!(err.to_string().contains(file!()));
+            err.into_cell()
+        });
+
+        let cell = t.join().unwrap();
+        assert_eq!(cell.checked_into_inner().ok(), Some(1));
+    }
+}
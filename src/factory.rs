@@ -0,0 +1,146 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::ops;
+
+use super::per_thread::PerThread;
+
+/// A `Send` factory for values that do not implement `Send`
+///
+/// Each thread that accesses the `SendFactory` gets its own value, which is created by the factory
+/// function on first access from that thread. This is useful for values that can be created on any
+/// thread but can't be moved between threads, like random number generators or FFI sessions.
+///
+/// # Warning
+///
+/// Each value is only dropped if the `SendFactory` is dropped on the thread of that value. Values
+/// of all other threads are leaked. The values of exited threads are kept until the `SendFactory`
+/// is dropped, unless they are removed with `SendFactory::clear_exited()`.
+///
+/// ```
+/// use send_cell::SendFactory;
+/// use std::rc::Rc;
+///
+/// let factory = SendFactory::new(|| Rc::new(String::from("session")));
+/// assert!(factory.try_get().is_none());
+/// assert_eq!(factory.get().as_str(), "session");
+///
+/// std::thread::scope(|s| {
+///     s.spawn(|| {
+///         assert!(factory.try_get().is_none());
+///         assert_eq!(factory.get().as_str(), "session");
+///     });
+/// });
+/// ```
+pub struct SendFactory<T> {
+    factory: Box<dyn Fn() -> T + Send + Sync>,
+    values: PerThread<T>,
+}
+
+impl<T> SendFactory<T> {
+    /// Creates a new `SendFactory` that creates the value of each thread with `factory`.
+    pub fn new<F: Fn() -> T + Send + Sync + 'static>(factory: F) -> Self {
+        SendFactory {
+            factory: Box::new(factory),
+            values: PerThread::new(),
+        }
+    }
+
+    /// Immutably borrows the value of the current thread, creating it first if needed.
+    pub fn get(&self) -> &T {
+        self.values.get_or_insert_with(|| (self.factory)())
+    }
+
+    /// Tries to immutably borrow the value of the current thread.
+    ///
+    /// `None` is returned if no value was created for the current thread yet.
+    pub fn try_get(&self) -> Option<&T> {
+        self.values.get()
+    }
+
+    /// Removes the values of threads that have exited and returns how many were removed.
+    ///
+    /// The values can't be dropped anymore and are leaked, but they don't take up space in the
+    /// `SendFactory` anymore. Exited threads are detected like for `SendCell::is_orphaned()`.
+    pub fn clear_exited(&mut self) -> usize {
+        self.values.clear_exited()
+    }
+}
+
+impl<T> ops::Deref for SendFactory<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<T> fmt::Debug for SendFactory<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("SendFactory")
+            .field("values", &self.values.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn value_per_thread() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let factory = {
+            let created = created.clone();
+            SendFactory::new(move || {
+                created.fetch_add(1, Ordering::SeqCst);
+                Rc::new(Cell::new(0))
+            })
+        };
+
+        assert!(factory.try_get().is_none());
+        factory.get().set(1);
+        assert_eq!(factory.try_get().map(|v| v.get()), Some(1));
+
+        let factory = thread::spawn(move || {
+            assert!(factory.try_get().is_none());
+            assert_eq!(factory.get().get(), 0);
+            factory
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(factory.get().get(), 1);
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn clear_exited() {
+        let factory = SendFactory::new(|| Rc::new(1));
+        assert_eq!(*factory.get(), Rc::new(1));
+
+        let mut factory = thread::spawn(move || {
+            factory.get();
+            factory
+        })
+        .join()
+        .unwrap();
+        assert_eq!(factory.values.len(), 2);
+
+        let cleared = factory.clear_exited();
+        if cleared == 1 {
+            assert_eq!(factory.values.len(), 1);
+        } else {
+            // Thread exits are not detected on this platform
+            assert_eq!((cleared, factory.values.len()), (0, 2));
+        }
+        assert!(factory.try_get().is_some());
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::marker::PhantomData;
+
+use super::SendCell;
+
+/// An opaque handle to a `SendCell` for passing it through C APIs
+///
+/// Pointers to this type are created with `SendCell::into_handle()` and can be passed to C code
+/// like any other opaque pointer. None of the functions working on handles panic if called from
+/// the wrong thread, which makes them suitable for use inside `extern "C"` functions.
+#[repr(C)]
+pub struct SendCellHandle {
+    _data: [u8; 0],
+    _marker: PhantomData<*mut u8>,
+}
+
+impl<T> SendCell<T> {
+    /// Consumes the `SendCell`, returning an opaque handle to it.
+    ///
+    /// The handle can be converted back with `SendCell::from_handle()` or freed with
+    /// `SendCell::free_handle()`, otherwise the `SendCell` is leaked.
+    pub fn into_handle(self) -> *mut SendCellHandle {
+        Box::into_raw(Box::new(self)) as *mut SendCellHandle
+    }
+
+    /// Converts an opaque handle back into a `SendCell`.
+    ///
+    /// This can be called from any thread.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been returned by `SendCell::<T>::into_handle()` with the same `T` and
+    /// must not be used anymore afterwards.
+    pub unsafe fn from_handle(handle: *mut SendCellHandle) -> Self {
+        *Box::from_raw(handle as *mut SendCell<T>)
+    }
+
+    /// Immutably borrows the value wrapped in the `SendCell` behind an opaque handle.
+    ///
+    /// `None` is returned if `handle` is `NULL` or if called from a different thread than the one
+    /// where the original value was created.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be `NULL` or must have been returned by `SendCell::<T>::into_handle()` with
+    /// the same `T` and must not have been freed yet. The returned reference must not outlive the
+    /// handle.
+    pub unsafe fn handle_get<'a>(handle: *const SendCellHandle) -> Option<&'a T> {
+        let cell = (handle as *const SendCell<T>).as_ref()?;
+        cell.try_get()
+    }
+
+    /// Frees the `SendCell` behind an opaque handle.
+    ///
+    /// `true` is returned if the `SendCell` was freed. If `handle` is `NULL` or if called from a
+    /// different thread than the one where the original value was created, nothing is freed and
+    /// `false` is returned.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be `NULL` or must have been returned by `SendCell::<T>::into_handle()` with
+    /// the same `T` and must not have been freed yet. If `true` is returned, the handle must not be
+    /// used anymore afterwards.
+    pub unsafe fn free_handle(handle: *mut SendCellHandle) -> bool {
+        match (handle as *const SendCell<T>).as_ref() {
+            Some(cell) if cell.try_get().is_some() => {
+                drop(SendCell::<T>::from_handle(handle));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+    use std::thread;
+
+    #[test]
+    fn handle_success() {
+        let handle = SendCell::new(1).into_handle();
+        unsafe {
+            assert_eq!(SendCell::<i32>::handle_get(handle), Some(&1));
+            assert!(SendCell::<i32>::free_handle(handle));
+        }
+
+        let handle = SendCell::new(2).into_handle();
+        let cell = unsafe { SendCell::<i32>::from_handle(handle) };
+        assert_eq!(cell.get(), &2);
+    }
+
+    #[test]
+    fn handle_null() {
+        unsafe {
+            assert_eq!(SendCell::<i32>::handle_get(ptr::null()), None);
+            assert!(!SendCell::<i32>::free_handle(ptr::null_mut()));
+        }
+    }
+
+    #[test]
+    fn handle_failure() {
+        let t = thread::spawn(move || SendCell::new(1).into_handle() as usize);
+
+        let r = t.join();
+        let handle = r.unwrap() as *mut SendCellHandle;
+
+        unsafe {
+            assert_eq!(SendCell::<i32>::handle_get(handle), None);
+            assert!(!SendCell::<i32>::free_handle(handle));
+        }
+        // The handle is leaked here as it can only be freed from the other thread
+    }
+}
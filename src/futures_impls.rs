@@ -0,0 +1,118 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Sink;
+
+use super::SendCell;
+
+impl<T: Unpin> SendCell<T> {
+    // Pins the wrapped value. Pinning is not structural as the value is moved out of the
+    // `SendCell` when it is dropped on another thread and has a dispatcher, so this is only
+    // available for `Unpin` values.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn pinned(self: Pin<&mut Self>) -> Pin<&mut T> {
+        let cell = self.get_mut();
+        cell.assert_thread();
+        Pin::new(&mut *cell.value)
+    }
+}
+
+/// Forwards to the wrapped sink.
+///
+/// This allows using a sink that does not implement `Send` with combinators that require `Send`,
+/// as long as it is only driven from the thread where it was created. Sinks that are not `Unpin`
+/// have to be pinned inside the `SendCell`, e.g. with `Box::pin()`.
+///
+/// # Panics
+///
+/// All methods panic if called from a different thread than the one where the original value was
+/// created.
+impl<T: Sink<Item> + Unpin, Item> Sink<Item> for SendCell<T> {
+    type Error = T::Error;
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.pinned().poll_ready(cx)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn start_send(self: Pin<&mut Self>, item: Item) -> Result<(), Self::Error> {
+        self.pinned().start_send(item)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.pinned().poll_flush(cx)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.pinned().poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::mem;
+    use std::panic;
+    use std::rc::Rc;
+    use std::task::Waker;
+    use std::thread;
+
+    struct VecSink(Rc<RefCell<Vec<i32>>>);
+
+    impl Sink<i32> for VecSink {
+        type Error = ();
+
+        fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: Pin<&mut Self>, item: i32) -> Result<(), ()> {
+            self.0.borrow_mut().push(item);
+            Ok(())
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn sink_success() {
+        let items = Rc::new(RefCell::new(Vec::new()));
+        let mut cell = SendCell::new(VecSink(items.clone()));
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut sink = Pin::new(&mut cell);
+        assert_eq!(sink.as_mut().poll_ready(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(sink.as_mut().start_send(1), Ok(()));
+        assert_eq!(sink.as_mut().poll_flush(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(sink.as_mut().poll_close(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(*items.borrow(), vec![1]);
+    }
+
+    #[test]
+    fn sink_failure() {
+        let t = thread::spawn(move || SendCell::new(VecSink(Rc::new(RefCell::new(Vec::new())))));
+
+        let r = t.join();
+        let mut cell = r.unwrap();
+        let r = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            Pin::new(&mut cell).start_send(1)
+        }));
+        assert!(r.is_err());
+        // The sink is leaked here as it can only be dropped from the other thread
+        mem::forget(cell);
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::error;
+use std::fmt;
+use std::sync::OnceLock;
+
+use super::{SendCell, ThreadToken};
+
+/// A thread-bound value for use in `static`s
+///
+/// The value is initialized on its owner thread with `SendGlobal::init()` or
+/// `SendGlobal::get_or_init()` and can afterwards only be accessed from that thread. A
+/// `&'static SendGlobal` can be used from any thread, and `SendGlobal::try_get()` reports with a
+/// `GlobalError` whether the value is not initialized yet or bound to another thread.
+///
+/// ```
+/// use send_cell::SendGlobal;
+/// use std::rc::Rc;
+///
+/// static DISPLAY: SendGlobal<Rc<String>> = SendGlobal::new();
+///
+/// let display = DISPLAY.get_or_init(|| Rc::new(String::from("display")));
+/// assert_eq!(display.as_str(), "display");
+///
+/// std::thread::spawn(|| {
+///     assert!(DISPLAY.try_get().is_err());
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+///
+/// # Warning
+///
+/// Values in `static`s are never dropped. If a `SendGlobal` is not used as a `static`, dropping it
+/// from a different thread than the one where the value was initialized results in a panic, same
+/// as dropping a `SendCell` from a different thread.
+pub struct SendGlobal<T> {
+    cell: OnceLock<SendCell<T>>,
+}
+
+impl<T> SendGlobal<T> {
+    /// Creates a new, uninitialized `SendGlobal`.
+    pub const fn new() -> Self {
+        SendGlobal {
+            cell: OnceLock::new(),
+        }
+    }
+
+    /// Initializes the `SendGlobal` with `value` and binds it to the current thread.
+    ///
+    /// If the `SendGlobal` was already initialized, `value` is returned as `Err(value)`.
+    pub fn init(&self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+        self.cell
+            .get_or_init(|| SendCell::new(value.take().unwrap()));
+
+        match value {
+            None => Ok(()),
+            Some(value) => Err(value),
+        }
+    }
+
+    /// Immutably borrows the value, initializing it with `f` and binding it to the current thread
+    /// if the `SendGlobal` was not initialized yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value was initialized on a different thread.
+    pub fn get_or_init<F: FnOnce() -> T>(&self, f: F) -> &T {
+        self.cell.get_or_init(|| SendCell::new(f())).get()
+    }
+
+    /// Immutably borrows the value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `SendGlobal` was not initialized yet or if called from a different thread
+    /// than the one where the value was initialized.
+    pub fn get(&self) -> &T {
+        match self.cell.get() {
+            Some(cell) => cell.get(),
+            None => panic!("{}", GlobalError::Uninitialized),
+        }
+    }
+
+    /// Tries to immutably borrow the value.
+    ///
+    /// An error is returned if the `SendGlobal` was not initialized yet or if called from a
+    /// different thread than the one where the value was initialized.
+    pub fn try_get(&self) -> Result<&T, GlobalError> {
+        self.cell
+            .get()
+            .ok_or(GlobalError::Uninitialized)?
+            .try_get()
+            .ok_or(GlobalError::InvalidThread)
+    }
+
+    /// Returns the `ThreadToken` of the thread the value is bound to, or `None` if the
+    /// `SendGlobal` was not initialized yet.
+    ///
+    /// This can be called from any thread.
+    pub fn thread_token(&self) -> Option<ThreadToken> {
+        self.cell.get().map(|cell| cell.thread_token())
+    }
+}
+
+impl<T> Default for SendGlobal<T> {
+    fn default() -> SendGlobal<T> {
+        SendGlobal::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SendGlobal<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.cell.get() {
+            Some(cell) => cell.fmt(f),
+            None => f.write_str("SendGlobal(<uninitialized>)"),
+        }
+    }
+}
+
+/// An error returned by `SendGlobal::try_get`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlobalError {
+    /// The value was not initialized yet.
+    Uninitialized,
+    /// The value was accessed from a different thread than the one where it was initialized.
+    InvalidThread,
+}
+
+impl fmt::Display for GlobalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            GlobalError::Uninitialized => f.write_str("global value not initialized yet"),
+            GlobalError::InvalidThread => f.write_str(
+                "global value accessed from a different thread than where it was initialized",
+            ),
+        }
+    }
+}
+
+impl error::Error for GlobalError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+    use SendCellViolation;
+
+    #[test]
+    fn init_success() {
+        let global = SendGlobal::new();
+        assert_eq!(global.try_get(), Err(GlobalError::Uninitialized));
+        assert_eq!(global.thread_token(), None);
+        assert_eq!(global.init(1), Ok(()));
+        assert_eq!(global.init(2), Err(2));
+        assert_eq!(global.get(), &1);
+        assert_eq!(global.get_or_init(|| 3), &1);
+        assert_eq!(global.thread_token(), Some(ThreadToken::current()));
+    }
+
+    #[test]
+    fn get_from_other_thread() {
+        static GLOBAL: SendGlobal<Rc<i32>> = SendGlobal::new();
+
+        assert_eq!(**GLOBAL.get_or_init(|| Rc::new(1)), 1);
+        let token = ThreadToken::current();
+
+        let t = thread::spawn(move || {
+            assert_eq!(GLOBAL.try_get().err(), Some(GlobalError::InvalidThread));
+            assert_eq!(GLOBAL.thread_token(), Some(token));
+            assert!(GLOBAL.init(Rc::new(2)).is_err());
+        });
+        t.join().unwrap();
+
+        let err = thread::spawn(move || {
+            GLOBAL.get();
+        })
+        .join()
+        .unwrap_err();
+        assert!(err.downcast_ref::<SendCellViolation>().is_some());
+
+        let t = thread::spawn(move || {
+            GLOBAL.get_or_init(|| Rc::new(2));
+        });
+        assert!(t.join().is_err());
+    }
+}
@@ -0,0 +1,448 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::error;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use super::oneshot;
+use super::{Dispatcher, Job, ThreadToken};
+
+/// A queue of closures that are run on the thread that created it
+///
+/// Any thread can queue closures via a `HomeHandle`, e.g. closures that own `SendCell`s bound to
+/// the home thread. The home thread runs them whenever it calls `HomeExecutor::pump()`, for
+/// example once per iteration of its event loop.
+///
+/// ```
+/// use send_cell::{HomeExecutor, SendCell};
+/// use std::rc::Rc;
+///
+/// let executor = HomeExecutor::new();
+/// let handle = executor.handle();
+/// let cell = SendCell::new(Rc::new(1));
+///
+/// let t = std::thread::spawn(move || handle.call(move || **cell.get() + 1));
+/// while !t.is_finished() {
+///     executor.pump_timeout(std::time::Duration::from_millis(10));
+/// }
+/// assert_eq!(t.join().unwrap(), Ok(2));
+/// ```
+pub struct HomeExecutor {
+    sender: mpsc::Sender<Job>,
+    receiver: mpsc::Receiver<Job>,
+    token: ThreadToken,
+    quit: Arc<AtomicBool>,
+    // Stays on the home thread so that queued closures are dropped there
+    _not_send: PhantomData<*const ()>,
+}
+
+impl HomeExecutor {
+    /// Creates a new `HomeExecutor` for the current thread.
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        HomeExecutor {
+            sender,
+            receiver,
+            token: ThreadToken::current(),
+            quit: Arc::new(AtomicBool::new(false)),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns a new handle for queueing closures from any thread.
+    pub fn handle(&self) -> HomeHandle {
+        HomeHandle {
+            sender: self.sender.clone(),
+            token: self.token,
+            quit: self.quit.clone(),
+        }
+    }
+
+    /// Returns the `ThreadToken` of the home thread.
+    pub fn thread_token(&self) -> ThreadToken {
+        self.token
+    }
+
+    /// Runs all currently queued closures without blocking and returns how many were run.
+    pub fn pump(&self) -> usize {
+        let mut count = 0;
+        while let Ok(job) = self.receiver.try_recv() {
+            job();
+            count += 1;
+        }
+        count
+    }
+
+    /// Waits up to `timeout` for a closure to be queued, then runs all queued closures and returns
+    /// how many were run.
+    pub fn pump_timeout(&self, timeout: Duration) -> usize {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(job) => {
+                job();
+                1 + self.pump()
+            }
+            Err(_) => 0,
+        }
+    }
+
+    /// Runs queued closures, blocking while there are none, until `HomeHandle::quit()` is called.
+    ///
+    /// This can be used as the main loop of threads that only handle calls from other threads.
+    pub fn run(&self) {
+        while !self.quit.swap(false, Ordering::SeqCst) {
+            match self.receiver.recv() {
+                Ok(job) => job(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl Default for HomeExecutor {
+    fn default() -> Self {
+        HomeExecutor::new()
+    }
+}
+
+impl fmt::Debug for HomeExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("HomeExecutor")
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+/// A handle for queueing closures on a `HomeExecutor` from any thread
+#[derive(Clone)]
+pub struct HomeHandle {
+    sender: mpsc::Sender<Job>,
+    token: ThreadToken,
+    quit: Arc<AtomicBool>,
+}
+
+impl HomeHandle {
+    /// Queues `f` to be run on the home thread.
+    ///
+    /// An error is returned if the `HomeExecutor` was dropped already.
+    pub fn spawn<F: FnOnce() + Send + 'static>(&self, f: F) -> Result<(), ExecutorDropped> {
+        self.try_spawn(f).map_err(|_| ExecutorDropped)
+    }
+
+    // Like `spawn()` but returns the closure if the `HomeExecutor` was dropped already
+    pub(crate) fn try_spawn<F: FnOnce() + Send + 'static>(&self, f: F) -> Result<(), Job> {
+        self.sender.send(Box::new(f)).map_err(|err| err.0)
+    }
+
+    /// Runs `f` on the home thread and waits for its result.
+    ///
+    /// If called from the home thread, `f` is run directly. Otherwise this blocks until the home
+    /// thread pumps the `HomeExecutor`, and an error is returned if the `HomeExecutor` is dropped
+    /// before `f` was run.
+    pub fn call<R, F>(&self, f: F) -> Result<R, ExecutorDropped>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        self.request(f).wait()
+    }
+
+    /// Queues `f` to be run on the home thread and returns an `AccessRequest` for its result.
+    ///
+    /// If called from the home thread, `f` is run directly. Otherwise the `AccessRequest` can be
+    /// awaited, waited on or polled for the result once the home thread pumps the `HomeExecutor`.
+    /// If the `AccessRequest` is dropped before `f` was run, `f` is not run anymore.
+    ///
+    /// If the `HomeExecutor` was dropped already, `f` is leaked as it might own values bound to the
+    /// home thread.
+    pub fn request<R, F>(&self, f: F) -> AccessRequest<R>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        AccessRequest::dispatch(self, f)
+    }
+
+    /// Makes `HomeExecutor::run()` return after the closure it is currently running.
+    ///
+    /// If the executor is not running, the next call of `HomeExecutor::run()` returns immediately.
+    pub fn quit(&self) {
+        self.quit.store(true, Ordering::SeqCst);
+        // Wake up the home thread if it is waiting for closures
+        let _ = self.try_spawn(|| ());
+    }
+
+    /// Returns the `ThreadToken` of the home thread.
+    ///
+    /// This can be called from any thread.
+    pub fn thread_token(&self) -> ThreadToken {
+        self.token
+    }
+}
+
+impl Dispatcher for HomeHandle {
+    fn thread_token(&self) -> ThreadToken {
+        self.token
+    }
+
+    fn dispatch(&self, job: Job) -> Result<(), Job> {
+        self.try_spawn(job)
+    }
+}
+
+impl fmt::Debug for HomeHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("HomeHandle")
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+/// The result of a closure queued with `HomeHandle::request()`
+///
+/// This is a future resolving to the result once the closure was run on the home thread, or to
+/// `Err(ExecutorDropped)` if the `HomeExecutor` was dropped before. The result can also be waited
+/// for with `AccessRequest::wait()` or checked for without blocking with
+/// `AccessRequest::try_wait()`.
+///
+/// Dropping the `AccessRequest` before the closure was run cancels it.
+pub struct AccessRequest<R> {
+    state: RequestState<R>,
+}
+
+enum RequestState<R> {
+    Pending(oneshot::Receiver<R>),
+    Done(Result<R, ExecutorDropped>),
+    Finished,
+}
+
+impl<R: Send + 'static> AccessRequest<R> {
+    // Returns an `AccessRequest` that is already done with `value` as result
+    pub(crate) fn ready(value: R) -> Self {
+        AccessRequest {
+            state: RequestState::Done(Ok(value)),
+        }
+    }
+
+    // Queues `f` on `dispatcher`, or runs it directly if called from the thread of `dispatcher`.
+    // If `dispatcher` can't run closures anymore, `f` is leaked as it might own values bound to
+    // that thread.
+    pub(crate) fn dispatch<D, F>(dispatcher: &D, f: F) -> Self
+    where
+        D: Dispatcher + ?Sized,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        if dispatcher.thread_token().is_current() {
+            return AccessRequest::ready(f());
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        let state = match dispatcher.dispatch(Box::new(move || {
+            if !sender.is_canceled() {
+                sender.send(f());
+            }
+        })) {
+            Ok(()) => RequestState::Pending(receiver),
+            Err(job) => {
+                mem::forget(job);
+                RequestState::Done(Err(ExecutorDropped))
+            }
+        };
+
+        AccessRequest { state }
+    }
+}
+
+impl<R> AccessRequest<R> {
+    /// Blocks the current thread until the closure was run and returns its result.
+    ///
+    /// This must not be called from the home thread while the closure is pending as the
+    /// `HomeExecutor` would never be pumped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result was already taken out by polling the `AccessRequest`.
+    pub fn wait(self) -> Result<R, ExecutorDropped> {
+        match self.state {
+            RequestState::Pending(receiver) => receiver.recv().ok_or(ExecutorDropped),
+            RequestState::Done(res) => res,
+            RequestState::Finished => panic!("`AccessRequest` polled after completion"),
+        }
+    }
+
+    /// Returns the result if the closure was run already, without blocking.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result was already taken out before.
+    pub fn try_wait(&mut self) -> Option<Result<R, ExecutorDropped>> {
+        match self.poll_with(|receiver| receiver.try_recv()) {
+            Poll::Ready(res) => Some(res),
+            Poll::Pending => None,
+        }
+    }
+
+    fn poll_with<P>(&mut self, poll: P) -> Poll<Result<R, ExecutorDropped>>
+    where
+        P: FnOnce(&mut oneshot::Receiver<R>) -> Poll<Option<R>>,
+    {
+        if let RequestState::Pending(ref mut receiver) = self.state {
+            match poll(receiver) {
+                Poll::Ready(res) => self.state = RequestState::Done(res.ok_or(ExecutorDropped)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        match mem::replace(&mut self.state, RequestState::Finished) {
+            RequestState::Done(res) => Poll::Ready(res),
+            _ => panic!("`AccessRequest` polled after completion"),
+        }
+    }
+}
+
+impl<R> Future for AccessRequest<R> {
+    type Output = Result<R, ExecutorDropped>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<R, ExecutorDropped>> {
+        self.get_mut().poll_with(|receiver| receiver.poll_recv(cx))
+    }
+}
+
+// The result is never pinned
+impl<R> Unpin for AccessRequest<R> {}
+
+impl<R> fmt::Debug for AccessRequest<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("AccessRequest")
+            .field("pending", &matches!(self.state, RequestState::Pending(_)))
+            .finish()
+    }
+}
+
+/// An error returned by `HomeHandle` if its `HomeExecutor` was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExecutorDropped;
+
+impl fmt::Display for ExecutorDropped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("home executor was dropped")
+    }
+}
+
+impl error::Error for ExecutorDropped {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::Waker;
+    use std::thread;
+    use SendCell;
+
+    #[test]
+    fn pump() {
+        let executor = HomeExecutor::new();
+        let handle = executor.handle();
+        let counter = SendCell::new(Rc::new(Cell::new(0)));
+        let cloned = counter.clone();
+
+        thread::spawn(move || {
+            handle
+                .spawn(move || cloned.get().set(cloned.get().get() + 1))
+                .unwrap();
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(counter.get().get(), 0);
+        assert_eq!(executor.pump(), 1);
+        assert_eq!(counter.get().get(), 1);
+        assert_eq!(executor.pump(), 0);
+    }
+
+    #[test]
+    fn call_on_home_thread() {
+        let executor = HomeExecutor::new();
+        assert_eq!(executor.handle().call(|| 1), Ok(1));
+        assert!(executor.thread_token().is_current());
+    }
+
+    #[test]
+    fn request() {
+        let executor = HomeExecutor::new();
+        let handle = executor.handle();
+        let value = SendCell::new(Rc::new(1));
+
+        let mut request = handle.request(|| 1);
+        assert_eq!(request.try_wait(), Some(Ok(1)));
+
+        let mut request = thread::spawn(move || handle.request(move || **value.get() + 1))
+            .join()
+            .unwrap();
+        assert_eq!(request.try_wait(), None);
+        assert_eq!(executor.pump(), 1);
+
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(Pin::new(&mut request).poll(&mut cx), Poll::Ready(Ok(2)));
+    }
+
+    #[test]
+    fn request_canceled() {
+        let executor = HomeExecutor::new();
+        let handle = executor.handle();
+        let counter = SendCell::new(Rc::new(Cell::new(0)));
+        let cloned = counter.clone();
+
+        thread::spawn(move || {
+            let request = handle.request(move || cloned.get().set(1));
+            drop(request);
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(executor.pump(), 1);
+        assert_eq!(counter.get().get(), 0);
+    }
+
+    #[test]
+    fn run_until_quit() {
+        let executor = HomeExecutor::new();
+        let handle = executor.handle();
+        let counter = SendCell::new(Rc::new(Cell::new(0)));
+        let cloned = counter.clone();
+
+        let t = thread::spawn(move || {
+            let r = handle.call(move || {
+                cloned.get().set(cloned.get().get() + 1);
+                cloned.get().get()
+            });
+            handle.quit();
+            r
+        });
+        executor.run();
+
+        assert_eq!(t.join().unwrap(), Ok(1));
+        assert_eq!(counter.get().get(), 1);
+    }
+
+    #[test]
+    fn executor_dropped() {
+        let executor = HomeExecutor::new();
+        let handle = executor.handle();
+        handle.spawn(|| ()).unwrap();
+        drop(executor);
+
+        assert_eq!(handle.spawn(|| ()), Err(ExecutorDropped));
+        let r = thread::spawn(move || handle.call(|| 1)).join().unwrap();
+        assert_eq!(r, Err(ExecutorDropped));
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::iter::FusedIterator;
+
+use super::SendCell;
+
+/// Extension trait for wrapping the items of an iterator into `SendCell`s
+pub trait SendCellIteratorExt: Iterator + Sized {
+    /// Returns an iterator that wraps each item into a new `SendCell`.
+    ///
+    /// Each `SendCell` is bound to the thread that retrieves the item from the iterator.
+    ///
+    /// ```
+    /// use send_cell::{SendCell, SendCellIteratorExt};
+    /// use std::rc::Rc;
+    ///
+    /// let cells: Vec<SendCell<Rc<i32>>> = (0..3).map(Rc::new).send_cells().collect();
+    /// assert_eq!(**cells[2].get(), 2);
+    /// ```
+    fn send_cells(self) -> SendCells<Self> {
+        SendCells { iter: self }
+    }
+}
+
+impl<I: Iterator> SendCellIteratorExt for I {}
+
+/// An iterator that wraps each item of another iterator into a `SendCell`
+///
+/// This is created by `SendCellIteratorExt::send_cells()`.
+#[derive(Debug, Clone)]
+pub struct SendCells<I> {
+    iter: I,
+}
+
+impl<I: Iterator> Iterator for SendCells<I> {
+    type Item = SendCell<I::Item>;
+
+    fn next(&mut self) -> Option<SendCell<I::Item>> {
+        self.iter.next().map(SendCell::new)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I: DoubleEndedIterator> DoubleEndedIterator for SendCells<I> {
+    fn next_back(&mut self) -> Option<SendCell<I::Item>> {
+        self.iter.next_back().map(SendCell::new)
+    }
+}
+
+impl<I: ExactSizeIterator> ExactSizeIterator for SendCells<I> {}
+
+impl<I: FusedIterator> FusedIterator for SendCells<I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn send_cells() {
+        let mut iter = vec![Rc::new(1), Rc::new(2), Rc::new(3)]
+            .into_iter()
+            .send_cells();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(**iter.next_back().unwrap().get(), 3);
+
+        let cells = iter.collect::<Vec<_>>();
+        assert!(cells.iter().all(|cell| cell.thread_token().is_current()));
+
+        let t = thread::spawn(move || {
+            assert!(cells.iter().all(|cell| cell.try_get().is_none()));
+            cells
+        });
+        let cells = t.join().unwrap();
+        assert_eq!(**cells[0].get(), 1);
+    }
+}
@@ -4,12 +4,198 @@
 
 //! An immutable memory location that implements `Send` for types that do not implement it
 
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "published")]
+extern crate arc_swap;
+#[cfg(feature = "async-executor")]
+extern crate async_channel;
+#[cfg(feature = "async-executor")]
+extern crate async_executor;
+#[cfg(feature = "crossbeam")]
+extern crate crossbeam_channel;
 extern crate fragile;
+#[cfg(feature = "futures")]
+extern crate futures;
+#[cfg(feature = "async-executor")]
+extern crate futures_core;
+#[cfg(all(feature = "objc2", target_vendor = "apple"))]
+extern crate objc2;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "rkyv")]
+extern crate rkyv;
+#[cfg(feature = "send_wrapper")]
+extern crate send_wrapper;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "thread_local")]
+extern crate thread_local;
 
 use std::cmp;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::mem::{self, ManuallyDrop};
 use std::ops;
+use std::panic::{self, RefUnwindSafe, UnwindSafe};
+use std::sync::atomic::AtomicBool;
+use std::thread;
+
+#[cfg(feature = "access_hook")]
+mod access_hook;
+#[cfg(feature = "access_hook")]
+pub use access_hook::{clear_access_hook, set_access_hook, AccessEvent};
+
+mod allow_list;
+pub use allow_list::AllowListCell;
+
+mod auto_cleanup;
+pub use auto_cleanup::AutoCleanupCell;
+
+mod bridge;
+pub use bridge::{spawn_local_bridged, BridgedOutput, Canceled, DriverHandle};
+
+mod borrows;
+use borrows::BorrowCount;
+
+mod builder;
+pub use builder::SendCellBuilder;
+
+mod dispatch;
+pub use dispatch::{DispatchError, Dispatcher, Job};
+
+mod dispose;
+pub use dispose::Dispose;
+
+mod drop_policy;
+pub use drop_policy::{
+    assert_no_recorded_drops, default_drop_policy, set_default_drop_policy, take_recorded_drops,
+    DropPolicy,
+};
+
+mod error;
+pub use error::{AccessError, GatherError, IntoInnerError, SendCellViolation, ViolationKind};
+
+mod ffi;
+pub use ffi::SendCellHandle;
+
+mod factory;
+pub use factory::SendFactory;
+
+mod global;
+pub use global::{GlobalError, SendGlobal};
+
+mod home_executor;
+pub use home_executor::{AccessRequest, ExecutorDropped, HomeExecutor, HomeHandle};
+
+mod iter;
+pub use iter::{SendCellIteratorExt, SendCells};
+
+mod ops_impls;
+
+mod oneshot;
+
+mod orphan;
+pub use orphan::OrphanSafe;
+
+mod owned_ref;
+pub use owned_ref::OwnedRef;
+
+mod per_thread;
+
+mod pinned_worker;
+pub use pinned_worker::PinnedWorker;
+
+mod poison;
+use poison::PoisonGuard;
+
+pub mod prelude;
+
+mod proxy;
+pub use proxy::SendProxy;
+
+mod rc_ref_cell;
+pub use rc_ref_cell::{BorrowError, SendRcRefCell};
+
+mod registry;
+pub use registry::Registry;
+
+mod replicated;
+pub use replicated::SendReplicated;
+
+mod scope;
+pub use scope::{scope, ScopedCells, ScopedSendCell};
+
+mod send_map;
+pub use send_map::{SendMap, SendMapRemover};
+
+mod send_ptr;
+pub use send_ptr::{SendNonNull, SendPtr};
+
+mod send_vec;
+pub use send_vec::SendVec;
+
+mod shared;
+pub use shared::{SendShared, WeakShared};
+
+mod storage;
+use storage::Storage;
+
+mod thread_token;
+pub use thread_token::ThreadToken;
+
+mod transfer;
+pub use transfer::TransferSafe;
+
+mod with_all;
+pub use with_all::{try_with_all, with_all, ForeignCellError, SendCellTuple};
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
+
+#[cfg(feature = "async-executor")]
+mod async_executor_dispatcher;
+#[cfg(feature = "async-executor")]
+pub use async_executor_dispatcher::LocalExecutorDispatcher;
+
+#[cfg(feature = "crossbeam")]
+mod crossbeam_dispatcher;
+#[cfg(feature = "crossbeam")]
+pub use crossbeam_dispatcher::{CrossbeamDispatcher, CrossbeamPump};
+
+#[cfg(feature = "futures")]
+mod futures_impls;
+
+#[cfg(all(feature = "objc2", target_vendor = "apple"))]
+mod objc2_impls;
+
+#[cfg(feature = "rayon")]
+mod per_worker;
+#[cfg(feature = "rayon")]
+pub use per_worker::SendCellPerWorker;
+
+#[cfg(feature = "published")]
+mod published;
+#[cfg(feature = "published")]
+pub use published::PublishedSendCell;
+
+#[cfg(feature = "rkyv")]
+mod rkyv_impls;
+
+#[cfg(feature = "serde")]
+mod serde_impls;
+
+#[cfg(feature = "cell_stats")]
+mod stats;
+#[cfg(feature = "cell_stats")]
+pub use stats::{live_cells, LiveCells};
+
+#[cfg(feature = "thread_local")]
+mod thread_local_impls;
+
+// The thread a `SendCell` is bound to and the location where it was created
+type Binding = (thread::ThreadId, &'static panic::Location<'static>);
 
 /// An immutable memory location that implements `Send` for types that do not implement it
 ///
@@ -23,267 +209,2228 @@ use std::ops;
 /// implemented on `SendCell` like `Eq`.
 ///
 /// Calling `drop` on a `SendCell` or otherwise freeing the value from a different thread than the
-/// one where it was created also results in a panic.
+/// one where it was created also results in a panic, unless a different policy was selected with
+/// `set_default_drop_policy()`. If the thread is already panicking, the value is leaked instead.
 pub struct SendCell<T> {
-    value: fragile::Fragile<T>,
+    // The value is only dropped by the `Drop` impl of the `SendCell` and can be moved out in
+    // `into_inner()`
+    value: Storage<T>,
+    thread_id: thread::ThreadId,
+    location: &'static panic::Location<'static>,
+    options: Option<Box<builder::Options<T>>>,
+    borrows: borrows::Counter,
+    poisoned: AtomicBool,
 }
 
 impl<T> SendCell<T> {
     /// Creates a new `SendCell` containing `value`.
+    #[track_caller]
     pub fn new(value: T) -> Self {
+        // Looks up the current thread together with counting the cell
+        let thread_id = orphan::add_current();
+        SendCell::with_counted_storage(Storage::new(value), (thread_id, panic::Location::caller()))
+    }
+
+    /// Creates a new `SendCell` for each of `values`.
+    ///
+    /// This is equivalent to calling `SendCell::new()` for each value but looks up the current
+    /// thread only once.
+    #[track_caller]
+    pub fn new_many<I: IntoIterator<Item = T>>(values: I) -> Vec<Self> {
+        let binding = (thread_token::current_thread_id(), panic::Location::caller());
+        values
+            .into_iter()
+            .map(|value| SendCell::with_binding(value, binding))
+            .collect()
+    }
+
+    /// Creates a new `SendCell` containing `value` that is additionally checked with `validator`
+    /// on every access.
+    ///
+    /// See `SendCellBuilder::validator()` for details.
+    #[track_caller]
+    pub fn new_with_validator(value: T, validator: fn(&T) -> bool) -> Self {
+        SendCell::builder().validator(validator).build(value)
+    }
+
+    // Creates a new `SendCell` for a value derived from the one of another `SendCell`, bound to the
+    // same thread and with the same creation location.
+    fn with_binding(value: T, binding: Binding) -> Self {
+        SendCell::with_storage(Storage::new(value), binding)
+    }
+
+    fn with_storage(value: Storage<T>, binding: Binding) -> Self {
+        orphan::add(binding.0);
+        SendCell::with_counted_storage(value, binding)
+    }
+
+    // Same as `with_storage()` for a `SendCell` that was already counted for orphan detection
+    fn with_counted_storage(value: Storage<T>, binding: Binding) -> Self {
+        #[cfg(feature = "cell_stats")]
+        stats::add::<T>(binding.0);
         SendCell {
-            value: fragile::Fragile::new(value),
+            value,
+            thread_id: binding.0,
+            location: binding.1,
+            options: None,
+            borrows: borrows::new_counter(),
+            poisoned: AtomicBool::new(false),
+        }
+    }
+
+    fn binding(&self) -> Binding {
+        (self.thread_id, self.location)
+    }
+
+    // Binds the `SendCell` to another thread
+    //
+    // Panics if the value was converted from a `fragile::Fragile` on a different thread than the
+    // one it is bound to, as it can only be moved out of the `Fragile` on that thread.
+    #[track_caller]
+    fn rebind(&mut self, thread_id: thread::ThreadId) {
+        if self.value.is_valid_foreign() == Some(false) {
+            panic::panic_any(SendCellViolation::new(self, ViolationKind::Access, None));
+        }
+
+        #[cfg(feature = "cell_stats")]
+        {
+            stats::remove::<T>(self.thread_id);
+            stats::add::<T>(thread_id);
+        }
+        orphan::remove(self.thread_id);
+        orphan::add(thread_id);
+        // Safety: The value is written back right away
+        let value = unsafe { self.value.take() };
+        self.value = Storage::new(value);
+        self.thread_id = thread_id;
+    }
+
+    // The thread the `SendCell` is bound to. For a `fragile::Fragile` that was converted on a
+    // different thread this is only known on its thread and a `ThreadId` of no running thread
+    // elsewhere.
+    fn bound_thread_id(&self) -> thread::ThreadId {
+        match self.value.is_valid_foreign() {
+            Some(true) => thread_token::current_thread_id(),
+            _ => self.thread_id,
+        }
+    }
+
+    // Returns `false` if the thread the `SendCell` is bound to is not known
+    fn is_bound_to_known_thread(&self) -> bool {
+        self.value.is_valid_foreign() != Some(false)
+    }
+
+    /// Creates a new `SendCell` containing `value` that is bound to the thread of `token`.
+    ///
+    /// This allows creating a value on one thread that can afterwards only be used on another
+    /// thread. As `value` is moved to the other thread, it has to implement `Send`. For values that
+    /// don't, see `SendCell::from_parts()`.
+    #[track_caller]
+    pub fn new_bound_to(value: T, token: ThreadToken) -> Self
+    where
+        T: Send,
+    {
+        // Safety: `T` is `Send` so it can be used and dropped on any thread
+        unsafe { SendCell::from_parts(value, token) }
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn is_valid(&self) -> bool {
+        self.is_valid_thread()
+            && !self.is_poisoned()
+            && self
+                .options
+                .as_ref()
+                .and_then(|options| options.validator)
+                .is_none_or(|validator| validator(&self.value))
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn is_valid_thread(&self) -> bool {
+        let valid = self.is_on_bound_thread();
+        #[cfg(feature = "access_hook")]
+        access_hook::report(self, valid, panic::Location::caller());
+        valid
+    }
+
+    // Same as `is_valid_thread()` but without reporting to the access hook
+    fn is_on_bound_thread(&self) -> bool {
+        self.value
+            .is_valid_foreign()
+            .unwrap_or_else(|| thread_token::current_thread_id() == self.thread_id)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn assert_thread(&self) {
+        if !self.is_valid() {
+            panic::panic_any(SendCellViolation::new(self, ViolationKind::Access, None));
+        }
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn assert_thread_expect(&self, msg: &str) {
+        if !self.is_valid() {
+            panic::panic_any(SendCellViolation::new(
+                self,
+                ViolationKind::Access,
+                Some(msg),
+            ));
+        }
+    }
+
+    /// Consumes the `SendCell`, returning the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn into_inner(self) -> T {
+        self.assert_thread();
+        self.take_inner()
+    }
+
+    // Moves the value out without checking the thread
+    fn take_inner(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+        #[cfg(feature = "cell_stats")]
+        stats::remove::<T>(this.thread_id);
+        orphan::remove(this.thread_id);
+        drop(this.options.take());
+        // Safety: `this` is not used anymore afterwards and its Drop impl is not run
+        unsafe { this.value.take() }
+    }
+
+    /// Consumes the `SendCell`, returning the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a message including `msg` if called from a different thread than the one where
+    /// the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn into_inner_expect(self, msg: &str) -> T {
+        self.assert_thread_expect(msg);
+        self.take_inner()
+    }
+
+    /// Consumes the `SendCell`, returning the wrapped value if successful.
+    ///
+    /// The wrapped value is returned if this is called from the same thread as the one where the
+    /// original value was created, otherwise the `SendCell` is returned as `Err(self)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_into_inner(self) -> Result<T, Self> {
+        if self.is_valid() {
+            Ok(self.take_inner())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Consumes the `SendCell`, returning the wrapped value if successful.
+    ///
+    /// This is the same as `SendCell::try_into_inner()` but returns an `IntoInnerError` with
+    /// details about the failure together with the `SendCell`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn checked_into_inner(self) -> Result<T, IntoInnerError<T>> {
+        self.try_into_inner().map_err(IntoInnerError::new)
+    }
+
+    /// Returns a raw pointer to the wrapped value.
+    ///
+    /// This can be called from any thread, e.g. for using the address as identity of the value.
+    /// The pointer must not be dereferenced from a different thread than the one where the
+    /// original value was created.
+    ///
+    /// The value is stored inside the `SendCell`, so the address is only stable while the
+    /// `SendCell` is not moved, e.g. to another thread. For an address that stays the same, put
+    /// the `SendCell` into a `Box` or `Arc` first.
+    ///
+    /// For a `SendCell` converted from a `fragile::Fragile` on a different thread than the one
+    /// where the `Fragile` was created, the address is only known on that thread and a null pointer
+    /// is returned on all other threads.
+    pub fn as_ptr(&self) -> *const T {
+        self.value.as_ptr()
+    }
+
+    /// Returns a mutable raw pointer to the wrapped value.
+    ///
+    /// This can be called from any thread, e.g. for using the address as identity of the value.
+    /// The pointer must not be dereferenced from a different thread than the one where the
+    /// original value was created.
+    ///
+    /// See `SendCell::as_ptr()` for `SendCell`s converted from a `fragile::Fragile`.
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.value.as_mut_ptr()
+    }
+
+    /// Returns the location where this `SendCell` was created.
+    ///
+    /// This can be called from any thread.
+    pub fn location(&self) -> &'static panic::Location<'static> {
+        self.location
+    }
+
+    /// Returns the `ThreadToken` of the thread this `SendCell` is bound to.
+    ///
+    /// This can be called from any thread.
+    ///
+    /// For a `SendCell` converted from a `fragile::Fragile` on a different thread than the one
+    /// where the `Fragile` was created, the thread is only known on that thread. On all other
+    /// threads a token that does not belong to any running thread is returned.
+    pub fn thread_token(&self) -> ThreadToken {
+        ThreadToken::from_thread_id(self.bound_thread_id())
+    }
+
+    /// Returns `true` if this `SendCell` is bound to the thread of `token`.
+    ///
+    /// This can be called from any thread.
+    pub fn is_bound_to(&self, token: &ThreadToken) -> bool {
+        self.is_bound_to_known_thread() && self.bound_thread_id() == token.thread_id()
+    }
+
+    /// Returns `true` if this `SendCell` and `other` are bound to the same thread.
+    ///
+    /// This can be called from any thread.
+    pub fn same_thread<U>(&self, other: &SendCell<U>) -> bool {
+        self.is_bound_to_known_thread()
+            && other.is_bound_to_known_thread()
+            && self.bound_thread_id() == other.bound_thread_id()
+    }
+
+    /// Consumes the `SendCell`, returning the wrapped value and a `ThreadToken` for the thread it
+    /// is bound to.
+    ///
+    /// The `SendCell` can be reconstructed from both with `SendCell::from_parts()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn into_parts(self) -> (T, ThreadToken) {
+        let token = self.thread_token();
+        (self.into_inner(), token)
+    }
+
+    /// Consumes the `SendCell`, returning the wrapped value and a `ThreadToken` for the thread it
+    /// is bound to if successful.
+    ///
+    /// The parts are returned if this is called from the same thread as the one where the original
+    /// value was created, otherwise the `SendCell` is returned as `Err(self)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_into_parts(self) -> Result<(T, ThreadToken), Self> {
+        if self.is_valid() {
+            let token = self.thread_token();
+            Ok((self.take_inner(), token))
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Creates a new `SendCell` containing `value` that is bound to the thread of `token`.
+    ///
+    /// This can be called from any thread.
+    ///
+    /// # Safety
+    ///
+    /// `value` must be safe to be used and dropped on the thread of `token`, e.g. because it was
+    /// previously returned together with `token` from `SendCell::into_parts()` and was not moved
+    /// to another thread since.
+    #[track_caller]
+    pub unsafe fn from_parts(value: T, token: ThreadToken) -> Self {
+        SendCell::with_binding(value, (token.thread_id(), panic::Location::caller()))
+    }
+
+    /// Immutably borrows the wrapped value.
+    ///
+    /// Multiple immutable borrows can be taken out at the same time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn get(&self) -> &T {
+        self.assert_thread();
+        &self.value
+    }
+
+    /// Immutably borrows the wrapped value.
+    ///
+    /// Multiple immutable borrows can be taken out at the same time.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a message including `msg` if called from a different thread than the one where
+    /// the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn get_expect(&self, msg: &str) -> &T {
+        self.assert_thread_expect(msg);
+        &self.value
+    }
+
+    /// Tries to immutably borrow the wrapped value.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created.
+    ///
+    /// Multiple immutable borrows can be taken out at the same time.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_get(&self) -> Option<&T> {
+        if self.is_valid() {
+            Some(&self.value)
+        } else {
+            None
+        }
+    }
+
+    /// Tries to immutably borrow the wrapped value.
+    ///
+    /// This is the same as `SendCell::try_get()` but returns an `AccessError` with details about
+    /// the failure.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn checked_get(&self) -> Result<&T, AccessError> {
+        self.try_get().ok_or_else(|| AccessError::new(self))
+    }
+
+    /// Immutably borrows the wrapped value.
+    ///
+    /// The borrow lasts until the returned `Ref` exits scope. Multiple immutable borrows can be
+    /// taken out at the same time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            value: self.get(),
+            borrow: BorrowCount::new(self),
+        }
+    }
+
+    /// Immutably borrows the wrapped value.
+    ///
+    /// The borrow lasts until the returned `Ref` exits scope. Multiple immutable borrows can be
+    /// taken out at the same time.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a message including `msg` if called from a different thread than the one where
+    /// the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn borrow_expect(&self, msg: &str) -> Ref<'_, T> {
+        Ref {
+            value: self.get_expect(msg),
+            borrow: BorrowCount::new(self),
+        }
+    }
+
+    /// Tries to immutably borrow the wrapped value.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created.
+    ///
+    /// The borrow lasts until the returned `Ref` exits scope. Multiple immutable borrows can be
+    /// taken out at the same time.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_borrow(&self) -> Option<Ref<'_, T>> {
+        self.try_get().map(|value| Ref {
+            value,
+            borrow: BorrowCount::new(self),
+        })
+    }
+
+    /// Immutably borrows the wrapped value with a guard that checks the thread again on every
+    /// dereference.
+    ///
+    /// This is meant for hardening code where a guard could end up on another thread through
+    /// `unsafe` code, e.g. an incorrect `unsafe impl Send` on a struct containing it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn borrow_checked(&self) -> CheckedRef<'_, T> {
+        self.assert_thread();
+        CheckedRef {
+            cell: self,
+            borrow: BorrowCount::new(self),
         }
     }
 
-    /// Consumes the `SendCell`, returning the wrapped value.
-    ///
-    /// # Panics
-    ///
-    /// Panics if called from a different thread than the one where the original value was created.
-    pub fn into_inner(self) -> T {
-        self.value.into_inner()
-    }
+    /// Tries to immutably borrow the wrapped value with a guard that checks the thread again on
+    /// every dereference.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_borrow_checked(&self) -> Option<CheckedRef<'_, T>> {
+        self.try_get().map(|_| CheckedRef {
+            cell: self,
+            borrow: BorrowCount::new(self),
+        })
+    }
+
+    /// Returns a clone of the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn get_cloned(&self) -> T
+    where
+        T: Clone,
+    {
+        self.get().clone()
+    }
+
+    /// Tries to return a clone of the wrapped value.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_get_cloned(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.try_get().cloned()
+    }
+
+    /// Returns a copy of the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn get_copy(&self) -> T
+    where
+        T: Copy,
+    {
+        *self.get()
+    }
+
+    /// Tries to return a copy of the wrapped value.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_get_copy(&self) -> Option<T>
+    where
+        T: Copy,
+    {
+        self.try_get().copied()
+    }
+
+    /// Replaces the wrapped value with the result of calling `f` with it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    ///
+    /// If `f` panics there is no value left in the `SendCell`. It is permanently poisoned
+    /// afterwards, see `SendCell::is_poisoned()`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn update<F: FnOnce(T) -> T>(&mut self, f: F) {
+        self.assert_thread();
+        self.update_unchecked(f);
+    }
+
+    // Replaces the value with the result of calling `f` with it without checking the thread
+    fn update_unchecked<F: FnOnce(T) -> T>(&mut self, f: F) {
+        // Marks the value as lost if `f` panics
+        struct LoseOnPanic<'a, T: 'a>(&'a mut SendCell<T>);
+
+        impl<'a, T> Drop for LoseOnPanic<'a, T> {
+            fn drop(&mut self) {
+                // The value was moved out already, so nothing is dropped here
+                self.0.value = Storage::Lost;
+            }
+        }
+
+        // Safety: The value is moved out and written back before anything else can access it,
+        // and the storage is marked as lost if `f` panics in between
+        let value = unsafe { self.value.take() };
+        let guard = LoseOnPanic(self);
+        let value = f(value);
+        // Safety: The old value was moved out above
+        unsafe { guard.0.value.restore(value) };
+        mem::forget(guard);
+    }
+
+    /// Tries to replace the wrapped value with the result of calling `f` with it.
+    ///
+    /// `None` is returned and `f` is not called if called from a different thread than the one
+    /// where the original value was created.
+    ///
+    /// If `f` panics there is no value left in the `SendCell`. It is permanently poisoned
+    /// afterwards, see `SendCell::is_poisoned()`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_update<F: FnOnce(T) -> T>(&mut self, f: F) -> Option<()> {
+        if self.is_valid() {
+            self.update_unchecked(f);
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    /// Calls `f` with the wrapped value and returns its result.
+    ///
+    /// The result is required to be `Send` so that it can be handed to other threads, e.g. for
+    /// creating a snapshot or summary of the wrapped value that is not bound to this thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn to_send_snapshot<U: Send, F: FnOnce(&T) -> U>(&self, f: F) -> U {
+        let value = self.get();
+        let _guard = PoisonGuard::new(&self.poisoned);
+        f(value)
+    }
+
+    /// Tries to call `f` with the wrapped value and returns its result.
+    ///
+    /// `None` is returned and `f` is not called if called from a different thread than the one
+    /// where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_to_send_snapshot<U: Send, F: FnOnce(&T) -> U>(&self, f: F) -> Option<U> {
+        let value = self.try_get()?;
+        let _guard = PoisonGuard::new(&self.poisoned);
+        Some(f(value))
+    }
+
+    /// Calls `f` with the wrapped value and returns `self` again.
+    ///
+    /// This allows inspecting the wrapped value, e.g. for logging, in the middle of a method chain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn inspect<F: FnOnce(&T)>(&self, f: F) -> &Self {
+        let value = self.get();
+        let _guard = PoisonGuard::new(&self.poisoned);
+        f(value);
+        self
+    }
+
+    /// Tries to call `f` with the wrapped value and returns `self` again.
+    ///
+    /// `None` is returned and `f` is not called if called from a different thread than the one
+    /// where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_inspect<F: FnOnce(&T)>(&self, f: F) -> Option<&Self> {
+        let value = self.try_get()?;
+        let _guard = PoisonGuard::new(&self.poisoned);
+        f(value);
+        Some(self)
+    }
+
+    /// Consumes the `SendCell`, calling `f` with the wrapped value and returning its result.
+    ///
+    /// The result is required to be `Send` so that it can be handed to other threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn map_send<U: Send, F: FnOnce(T) -> U>(self, f: F) -> U {
+        f(self.into_inner())
+    }
+
+    /// Consumes the `SendCell`, calling `f` with the wrapped value and returning its result if
+    /// successful.
+    ///
+    /// The result is returned if this is called from the same thread as the one where the original
+    /// value was created, otherwise `f` is not called and the `SendCell` is returned as
+    /// `Err(self)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_map_send<U: Send, F: FnOnce(T) -> U>(self, f: F) -> Result<U, Self> {
+        self.try_into_inner().map(f)
+    }
+
+    /// Consumes this and another `SendCell`, returning a new `SendCell` containing both values.
+    ///
+    /// The new `SendCell` is bound to the same thread as `self` and keeps its name and drop
+    /// policy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where either of the original values
+    /// was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn zip<U>(self, other: SendCell<U>) -> SendCell<(T, U)> {
+        let derive = self.derive();
+        derive((self.into_inner(), other.into_inner()))
+    }
+
+    /// Consumes this and another `SendCell`, returning a new `SendCell` containing both values if
+    /// successful.
+    ///
+    /// The new `SendCell` is returned if this is called from the same thread as the one where both
+    /// original values were created, otherwise both `SendCell`s are returned as
+    /// `Err((self, other))`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    #[allow(clippy::type_complexity)]
+    pub fn try_zip<U>(self, other: SendCell<U>) -> Result<SendCell<(T, U)>, (Self, SendCell<U>)> {
+        if self.is_valid() && other.is_valid() {
+            let derive = self.derive();
+            Ok(derive((self.take_inner(), other.take_inner())))
+        } else {
+            Err((self, other))
+        }
+    }
+
+    /// Consumes the `SendCell`, returning a `fragile::Fragile` of the wrapped value.
+    ///
+    /// The `Fragile` is bound to the same thread as the `SendCell`. A `SendCell` that was converted
+    /// from a `Fragile` on a different thread than the one it is bound to still contains that
+    /// `Fragile`, so it can be converted back from any thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was
+    /// created, unless the `SendCell` still contains a `Fragile`, as no `Fragile` bound to that
+    /// thread can be created here.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn into_fragile(self) -> fragile::Fragile<T> {
+        match self.try_into_fragile() {
+            Ok(fragile) => fragile,
+            Err(cell) => fragile::Fragile::new(cell.into_inner()),
+        }
+    }
+
+    /// Consumes the `SendCell`, returning a `fragile::Fragile` of the wrapped value if successful.
+    ///
+    /// This only fails in the cases where `SendCell::into_fragile()` panics, in which case the
+    /// `SendCell` is returned as `Err(self)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_into_fragile(self) -> Result<fragile::Fragile<T>, Self> {
+        let mut this = ManuallyDrop::new(self);
+        // Safety: `this` is not used anymore afterwards if the value was moved out
+        match unsafe { this.value.take_fragile() } {
+            Some(fragile) => {
+                #[cfg(feature = "cell_stats")]
+                stats::remove::<T>(this.thread_id);
+                orphan::remove(this.thread_id);
+                drop(this.options.take());
+                Ok(fragile)
+            }
+            None => ManuallyDrop::into_inner(this)
+                .try_into_inner()
+                .map(fragile::Fragile::new),
+        }
+    }
+}
+
+impl<A, B> SendCell<(A, B)> {
+    /// Consumes the `SendCell`, returning a `SendCell` for each of the two wrapped values.
+    ///
+    /// Both new `SendCell`s are bound to the same thread as the original one and keep its name and
+    /// drop policy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn unzip(self) -> (SendCell<A>, SendCell<B>) {
+        let (derive_a, derive_b) = (self.derive(), self.derive());
+        let (a, b) = self.into_inner();
+        (derive_a(a), derive_b(b))
+    }
+
+    /// Consumes the `SendCell`, returning a `SendCell` for each of the two wrapped values if
+    /// successful.
+    ///
+    /// The new `SendCell`s are returned if this is called from the same thread as the one where the
+    /// original value was created, otherwise the `SendCell` is returned as `Err(self)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_unzip(self) -> Result<(SendCell<A>, SendCell<B>), Self> {
+        let (derive_a, derive_b) = (self.derive(), self.derive());
+        let (a, b) = self.try_into_inner()?;
+        Ok((derive_a(a), derive_b(b)))
+    }
+}
+
+impl<T> SendCell<Option<T>> {
+    /// Consumes the `SendCell`, returning `None` if the wrapped value is `None` or otherwise a
+    /// `SendCell` containing the value inside the `Some`.
+    ///
+    /// The new `SendCell` is bound to the same thread as the original one and keeps its name and
+    /// drop policy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn transpose(self) -> Option<SendCell<T>> {
+        let derive = self.derive();
+        self.into_inner().map(derive)
+    }
+
+    /// Consumes the `SendCell`, returning `None` if the wrapped value is `None` or otherwise a
+    /// `SendCell` containing the value inside the `Some` if successful.
+    ///
+    /// The result is returned if this is called from the same thread as the one where the original
+    /// value was created, otherwise the `SendCell` is returned as `Err(self)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_transpose(self) -> Result<Option<SendCell<T>>, Self> {
+        let derive = self.derive();
+        self.try_into_inner().map(|v| v.map(derive))
+    }
+
+    /// Inserts the value computed from `f` into the wrapped `Option` if it is `None`, then returns
+    /// a mutable reference to the contained value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn get_or_insert_with<F: FnOnce() -> T>(&mut self, f: F) -> &mut T {
+        self.assert_thread();
+        let _guard = PoisonGuard::new(&self.poisoned);
+        self.value.get_or_insert_with(f)
+    }
+
+    /// Tries to insert the value computed from `f` into the wrapped `Option` if it is `None`, then
+    /// returns a mutable reference to the contained value.
+    ///
+    /// `None` is returned and `f` is not called if called from a different thread than the one
+    /// where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_get_or_insert_with<F: FnOnce() -> T>(&mut self, f: F) -> Option<&mut T> {
+        if self.is_valid() {
+            let _guard = PoisonGuard::new(&self.poisoned);
+            Some(self.value.get_or_insert_with(f))
+        } else {
+            None
+        }
+    }
+}
+
+impl<T, E> SendCell<Result<T, E>> {
+    /// Consumes the `SendCell`, returning the error if the wrapped value is an `Err` or otherwise a
+    /// `SendCell` containing the value inside the `Ok`.
+    ///
+    /// The new `SendCell` is bound to the same thread as the original one and keeps its name and
+    /// drop policy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn transpose(self) -> Result<SendCell<T>, E> {
+        let derive = self.derive();
+        self.into_inner().map(derive)
+    }
+
+    /// Consumes the `SendCell`, returning the error if the wrapped value is an `Err` or otherwise a
+    /// `SendCell` containing the value inside the `Ok` if successful.
+    ///
+    /// The result is returned if this is called from the same thread as the one where the original
+    /// value was created, otherwise the `SendCell` is returned as `Err(self)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_transpose(self) -> Result<Result<SendCell<T>, E>, Self> {
+        let derive = self.derive();
+        self.try_into_inner().map(|v| v.map(derive))
+    }
+}
+
+impl<T> SendCell<SendCell<T>> {
+    /// Consumes the `SendCell`, returning the wrapped `SendCell`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where either the original value or
+    /// the wrapped `SendCell`'s value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn flatten(self) -> SendCell<T> {
+        let inner = self.into_inner();
+        inner.assert_thread();
+        inner
+    }
+
+    /// Consumes the `SendCell`, returning the wrapped `SendCell` if successful.
+    ///
+    /// The wrapped `SendCell` is returned if this is called from the same thread as the one where
+    /// both the original value and the wrapped `SendCell`'s value were created, otherwise the
+    /// `SendCell` is returned as `Err(self)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_flatten(self) -> Result<SendCell<T>, Self> {
+        let binding = self.binding();
+        let options = self.options.clone();
+        let inner = self.try_into_inner()?;
+        if inner.is_valid() {
+            Ok(inner)
+        } else {
+            let mut cell = SendCell::with_binding(inner, binding);
+            cell.options = options;
+            Err(cell)
+        }
+    }
+}
+
+impl<T> SendCell<Vec<T>> {
+    /// Consumes the `SendCell`, returning a `SendCell` for each element of the wrapped `Vec`.
+    ///
+    /// All new `SendCell`s are bound to the same thread as the original one and keep its name and
+    /// drop policy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn scatter(self) -> Vec<SendCell<T>> {
+        self.assert_thread();
+        self.scatter_unchecked()
+    }
+
+    // Splits the value without checking the thread
+    fn scatter_unchecked(self) -> Vec<SendCell<T>> {
+        let derive = self.derive();
+        self.take_inner().into_iter().map(derive).collect()
+    }
+
+    /// Consumes the `SendCell`, returning a `SendCell` for each element of the wrapped `Vec` if
+    /// successful.
+    ///
+    /// The new `SendCell`s are returned if this is called from the same thread as the one where the
+    /// original value was created, otherwise the `SendCell` is returned as `Err(self)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_scatter(self) -> Result<Vec<SendCell<T>>, Self> {
+        if self.is_valid() {
+            Ok(self.scatter_unchecked())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Combines `cells` into a single `SendCell` containing a `Vec` of their values.
+    ///
+    /// All cells have to be bound to the current thread, which the new `SendCell` is bound to as
+    /// well. Otherwise a `GatherError` with the index of the first cell bound to a different thread
+    /// is returned, which also gives back all cells.
+    #[track_caller]
+    pub fn gather(cells: Vec<SendCell<T>>) -> Result<Self, GatherError<T>> {
+        if let Some(index) = cells.iter().position(|cell| !cell.is_valid()) {
+            return Err(GatherError::new(cells, index));
+        }
+
+        Ok(SendCell::new(
+            cells.into_iter().map(SendCell::take_inner).collect(),
+        ))
+    }
+}
+
+/// The `SendCell` is bound to the same thread as the `Fragile`, so this can be called from any
+/// thread.
+impl<T> From<fragile::Fragile<T>> for SendCell<T> {
+    #[track_caller]
+    fn from(fragile: fragile::Fragile<T>) -> SendCell<T> {
+        let thread_id = if fragile.is_valid() {
+            thread_token::current_thread_id()
+        } else {
+            thread_token::unknown_thread_id()
+        };
+        SendCell::with_storage(
+            Storage::from_fragile(fragile),
+            (thread_id, panic::Location::caller()),
+        )
+    }
+}
+
+#[cfg(feature = "send_wrapper")]
+impl<T> SendCell<T> {
+    /// Creates a new `SendCell` from a `send_wrapper::SendWrapper`.
+    ///
+    /// The `SendCell` is bound to the same thread as the `SendWrapper`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendWrapper` was created.
+    #[track_caller]
+    pub fn from_send_wrapper(wrapper: send_wrapper::SendWrapper<T>) -> Self {
+        SendCell::new(wrapper.take())
+    }
+
+    /// Tries to create a new `SendCell` from a `send_wrapper::SendWrapper`.
+    ///
+    /// The `SendCell` is returned if this is called from the same thread as the one where the
+    /// `SendWrapper` was created, otherwise the `SendWrapper` is returned as `Err(wrapper)`.
+    #[track_caller]
+    pub fn try_from_send_wrapper(
+        wrapper: send_wrapper::SendWrapper<T>,
+    ) -> Result<Self, send_wrapper::SendWrapper<T>> {
+        if wrapper.valid() {
+            Ok(SendCell::new(wrapper.take()))
+        } else {
+            Err(wrapper)
+        }
+    }
+
+    /// Consumes the `SendCell`, returning a `send_wrapper::SendWrapper` of the wrapped value.
+    ///
+    /// The `SendWrapper` is bound to the same thread as the `SendCell`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    pub fn into_send_wrapper(self) -> send_wrapper::SendWrapper<T> {
+        send_wrapper::SendWrapper::new(self.into_inner())
+    }
+
+    /// Consumes the `SendCell`, returning a `send_wrapper::SendWrapper` of the wrapped value if
+    /// successful.
+    ///
+    /// The `SendWrapper` is returned if this is called from the same thread as the one where the
+    /// original value was created, otherwise the `SendCell` is returned as `Err(self)`.
+    pub fn try_into_send_wrapper(self) -> Result<send_wrapper::SendWrapper<T>, Self> {
+        self.try_into_inner().map(send_wrapper::SendWrapper::new)
+    }
+}
+
+impl<T> From<T> for SendCell<T> {
+    #[track_caller]
+    fn from(t: T) -> SendCell<T> {
+        SendCell::new(t)
+    }
+}
+
+/// Collects into a new `SendCell` that is bound to the current thread.
+impl<A, T: FromIterator<A>> FromIterator<A> for SendCell<T> {
+    #[track_caller]
+    fn from_iter<I: IntoIterator<Item = A>>(iter: I) -> SendCell<T> {
+        SendCell::new(iter.into_iter().collect())
+    }
+}
+
+impl<T: Default> Default for SendCell<T> {
+    #[track_caller]
+    fn default() -> SendCell<T> {
+        SendCell::new(T::default())
+    }
+}
+
+impl<T: Clone> Clone for SendCell<T> {
+    #[track_caller]
+    fn clone(&self) -> SendCell<T> {
+        SendCell::new(self.get().clone())
+    }
+}
+
+/// Formats the wrapped value if called from the thread where it was created, otherwise only the
+/// thread the value is bound to is printed instead of panicking.
+impl<T: fmt::Debug> fmt::Debug for SendCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.try_get() {
+            Some(value) => value.fmt(f),
+            None if self.is_on_bound_thread() && self.is_poisoned() => {
+                f.write_str("SendCell(<poisoned>)")
+            }
+            None if self.is_on_bound_thread() => f.write_str("SendCell(<invalid>)"),
+            None if !self.is_bound_to_known_thread() => {
+                f.write_str("SendCell(<bound to unknown thread>)")
+            }
+            None => write!(f, "SendCell(<bound to {:?}>)", self.thread_id),
+        }
+    }
+}
+
+impl<T> SendCell<T> {
+    /// Tries to compare the wrapped values of both `SendCell`s for equality.
+    ///
+    /// Unlike the `PartialEq` implementation this does not panic but returns `None` if called from
+    /// a different thread than the one where either of the original values was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_eq(&self, other: &Self) -> Option<bool>
+    where
+        T: PartialEq,
+    {
+        Some(self.try_get()?.eq(other.try_get()?))
+    }
+
+    /// Tries to compare the wrapped values of both `SendCell`s.
+    ///
+    /// Unlike the `PartialOrd` implementation this does not panic but returns `None` if called from
+    /// a different thread than the one where either of the original values was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_partial_cmp(&self, other: &Self) -> Option<Option<cmp::Ordering>>
+    where
+        T: PartialOrd,
+    {
+        Some(self.try_get()?.partial_cmp(other.try_get()?))
+    }
+
+    /// Tries to compare the wrapped values of both `SendCell`s.
+    ///
+    /// Unlike the `Ord` implementation this does not panic but returns `None` if called from a
+    /// different thread than the one where either of the original values was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_cmp(&self, other: &Self) -> Option<cmp::Ordering>
+    where
+        T: Ord,
+    {
+        Some(self.try_get()?.cmp(other.try_get()?))
+    }
+
+    /// Tries to feed the wrapped value into `state`.
+    ///
+    /// Unlike the `Hash` implementation this does not panic but returns `None` without modifying
+    /// `state` if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_hash<H: Hasher>(&self, state: &mut H) -> Option<()>
+    where
+        T: Hash,
+    {
+        self.try_get().map(|v| v.hash(state))
+    }
+}
+
+impl<T: PartialEq> PartialEq<SendCell<T>> for SendCell<T> {
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn eq(&self, other: &Self) -> bool {
+        self.get().eq(other.get())
+    }
+}
+impl<T: Eq> Eq for SendCell<T> {}
+
+impl<T: PartialOrd> PartialOrd<SendCell<T>> for SendCell<T> {
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        self.get().partial_cmp(other.get())
+    }
+}
+impl<T: Ord> Ord for SendCell<T> {
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.get().cmp(other.get())
+    }
+}
+
+impl<T: Hash> Hash for SendCell<T> {
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.get().hash(state)
+    }
+}
+
+/// Extends the wrapped collection.
+///
+/// # Panics
+///
+/// Panics if called from a different thread than the one where the original value was created.
+impl<A, T: Extend<A>> Extend<A> for SendCell<T> {
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn extend<I: IntoIterator<Item = A>>(&mut self, iter: I) {
+        self.assert_thread();
+        self.value.extend(iter)
+    }
+}
+
+/// Forwards to the wrapped hasher.
+///
+/// # Panics
+///
+/// All methods panic if called from a different thread than the one where the original value was
+/// created.
+impl<T: Hasher> Hasher for SendCell<T> {
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn finish(&self) -> u64 {
+        self.get().finish()
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write(&mut self, bytes: &[u8]) {
+        self.assert_thread();
+        self.value.write(bytes)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_u8(&mut self, i: u8) {
+        self.assert_thread();
+        self.value.write_u8(i)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_u16(&mut self, i: u16) {
+        self.assert_thread();
+        self.value.write_u16(i)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_u32(&mut self, i: u32) {
+        self.assert_thread();
+        self.value.write_u32(i)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_u64(&mut self, i: u64) {
+        self.assert_thread();
+        self.value.write_u64(i)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_u128(&mut self, i: u128) {
+        self.assert_thread();
+        self.value.write_u128(i)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_usize(&mut self, i: usize) {
+        self.assert_thread();
+        self.value.write_usize(i)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_i8(&mut self, i: i8) {
+        self.assert_thread();
+        self.value.write_i8(i)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_i16(&mut self, i: i16) {
+        self.assert_thread();
+        self.value.write_i16(i)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_i32(&mut self, i: i32) {
+        self.assert_thread();
+        self.value.write_i32(i)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_i64(&mut self, i: i64) {
+        self.assert_thread();
+        self.value.write_i64(i)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_i128(&mut self, i: i128) {
+        self.assert_thread();
+        self.value.write_i128(i)
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn write_isize(&mut self, i: isize) {
+        self.assert_thread();
+        self.value.write_isize(i)
+    }
+}
+
+impl<T> Drop for SendCell<T> {
+    fn drop(&mut self) {
+        self.check_borrows_on_drop();
+        // Also a leaked value is not referenced by a `SendCell` anymore
+        orphan::remove(self.thread_id);
+
+        // Dropping is not an access, so the validator is not checked here. There is nothing to
+        // drop if the value was lost.
+        if mem::needs_drop::<T>() && !self.value.is_lost() && !self.is_on_bound_thread() {
+            let violation = SendCellViolation::new(self, ViolationKind::Drop, None);
+            let options = self.options.take();
+            if let Some((ref dispatcher, forward)) =
+                options.as_ref().and_then(|o| o.dispatcher.as_ref())
+            {
+                // Safety: `self.value` is not used anymore afterwards
+                let value = unsafe { self.value.take() };
+                if forward(value, self.binding(), &**dispatcher) {
+                    #[cfg(feature = "cell_stats")]
+                    stats::remove::<T>(self.thread_id);
+                    return;
+                }
+            }
+            let policy = options.as_ref().and_then(|o| o.drop_policy);
+            drop_policy::foreign_drop(violation, policy.unwrap_or_else(default_drop_policy));
+            // The value was leaked and stays counted
+            return;
+        }
+
+        if mem::needs_drop::<T>() {
+            // Safety: `self.value` is not used anymore afterwards
+            unsafe { self.value.drop_in_place() };
+        }
+
+        #[cfg(feature = "cell_stats")]
+        stats::remove::<T>(self.thread_id);
+    }
+}
+
+/// Formats the address of the wrapped value.
+///
+/// The value itself is not accessed, so this can be called from any thread. Like for
+/// `SendCell::as_ptr()`, the address changes whenever the `SendCell` is moved.
+impl<T> fmt::Pointer for SendCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Pointer::fmt(&self.as_ptr(), f)
+    }
+}
+
+unsafe impl<T> Send for SendCell<T> {}
+unsafe impl<T> Sync for SendCell<T> {}
+
+// Accessing the value from the wrong thread panics before the value is touched, so it can never be
+// observed in an inconsistent state because of that. Any other panic safety only depends on `T`.
+impl<T: UnwindSafe> UnwindSafe for SendCell<T> {}
+impl<T: RefUnwindSafe> RefUnwindSafe for SendCell<T> {}
+
+/// Wraps a borrowed reference to a value in a `SendCell` box.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ref<'a, T: 'a + ?Sized> {
+    value: &'a T,
+    borrow: BorrowCount<'a>,
+}
+
+impl<'a, T: 'a + Clone> Ref<'a, T> {
+    /// Returns a clone of the borrowed value.
+    pub fn cloned(&self) -> T {
+        self.value.clone()
+    }
+}
+
+impl<'a, T: 'a + ?Sized + ops::Deref> Ref<'a, T> {
+    /// Returns a `Ref` to the target of the borrowed smart pointer, e.g. the `U` of a `Box<U>` or
+    /// `Rc<U>`.
+    ///
+    /// This is an associated function that needs to be used as `Ref::as_deref(r)` so that it does
+    /// not conflict with methods of the same name on the borrowed value.
+    pub fn as_deref(r: Self) -> Ref<'a, T::Target> {
+        Ref {
+            value: &**r.value,
+            borrow: r.borrow,
+        }
+    }
+}
+
+impl<'a, T: 'a + ?Sized> Clone for Ref<'a, T> {
+    fn clone(&self) -> Ref<'a, T> {
+        Ref {
+            value: self.value,
+            borrow: self.borrow.clone(),
+        }
+    }
+}
+
+impl<'a, T: 'a + ?Sized> ops::Deref for Ref<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'a, T: 'a + ?Sized + fmt::Debug> fmt::Debug for Ref<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("Ref").field("value", &self.value).finish()
+    }
+}
+
+impl<'a, T: 'a + ?Sized + fmt::Display> fmt::Display for Ref<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.value.fmt(f)
+    }
+}
+
+/// Wraps a borrowed reference to a value in a `SendCell` box and checks the thread on every access.
+///
+/// This is created by `SendCell::borrow_checked()`.
+pub struct CheckedRef<'a, T: 'a> {
+    cell: &'a SendCell<T>,
+    borrow: BorrowCount<'a>,
+}
+
+impl<'a, T: 'a> CheckedRef<'a, T> {
+    /// Tries to return the borrowed value.
+    ///
+    /// `None` is returned in the same cases as with `SendCell::try_get()`, e.g. if called from a
+    /// different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_get(&self) -> Option<&T> {
+        self.cell.try_get()
+    }
+}
+
+impl<'a, T: 'a> Clone for CheckedRef<'a, T> {
+    fn clone(&self) -> CheckedRef<'a, T> {
+        CheckedRef {
+            cell: self.cell,
+            borrow: self.borrow.clone(),
+        }
+    }
+}
+
+/// Returns the borrowed value.
+///
+/// # Panics
+///
+/// Panics if called from a different thread than the one where the original value was created.
+impl<'a, T: 'a> ops::Deref for CheckedRef<'a, T> {
+    type Target = T;
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn deref(&self) -> &T {
+        self.cell.assert_thread();
+        &self.cell.value
+    }
+}
+
+impl<'a, T: 'a + fmt::Debug> fmt::Debug for CheckedRef<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("CheckedRef")
+            .field("value", &self.cell)
+            .finish()
+    }
+}
+
+impl<'a, T: 'a + fmt::Display> fmt::Display for CheckedRef<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        (**self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::mem;
+    use std::panic;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn get_success() {
+        let cell = SendCell::new(1);
+        assert_eq!(cell.get(), &1);
+        assert_eq!(cell.try_get(), Some(&1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_failure() {
+        let t = thread::spawn(move || {
+            let cell = SendCell::new(1);
+            assert_eq!(cell.get(), &1);
+            cell
+        });
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        // The cell is dropped while unwinding, which leaks it instead of panicking again
+        cell.get();
+    }
+
+    #[test]
+    fn try_get_failure() {
+        let t = thread::spawn(move || {
+            let cell = SendCell::new(1);
+            assert_eq!(cell.get(), &1);
+            cell
+        });
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        assert_eq!(cell.try_get(), None);
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+
+    #[test]
+    fn debug_success() {
+        let cell = SendCell::new(1);
+        assert_eq!(format!("{:?}", cell), "1");
+    }
+
+    #[test]
+    fn debug_other_thread() {
+        let t = thread::spawn(move || (SendCell::new(1), thread::current().id()));
+
+        let r = t.join();
+        let (cell, thread_id) = r.unwrap();
+
+        assert_eq!(
+            format!("{:?}", cell),
+            format!("SendCell(<bound to {:?}>)", thread_id)
+        );
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+
+    #[test]
+    fn debug_poisoned_invalid() {
+        let cell = SendCell::new(1);
+        let _ = panic::catch_unwind(|| cell.inspect(|_| panic!("inspect")));
+        assert_eq!(format!("{:?}", cell), "SendCell(<poisoned>)");
+        cell.clear_poison();
+        assert_eq!(format!("{:?}", cell), "1");
+
+        let cell = SendCell::new_with_validator(1, |_| false);
+        assert_eq!(format!("{:?}", cell), "SendCell(<invalid>)");
+    }
+
+    #[test]
+    fn pointer_other_thread() {
+        let t = thread::spawn(move || {
+            let cell = Box::new(SendCell::new(1));
+            let ptr = format!("{:p}", cell.get());
+            (cell, ptr)
+        });
+
+        let r = t.join();
+        let (cell, ptr) = r.unwrap();
+
+        assert_eq!(format!("{:p}", *cell), ptr);
+        assert_eq!(format!("{:p}", cell.as_ptr()), ptr);
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+
+    #[test]
+    fn pointer_moved_cell() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let t = thread::spawn(move || {
+            let cell = SendCell::new([1u8; 16]);
+            let ptr = cell.get().as_ptr() as usize;
+            sender.send(cell).unwrap();
+            ptr
+        });
+
+        let cell = receiver.recv().unwrap();
+        let _ = t.join().unwrap();
+
+        // The address is the one of the value at the current location of the cell
+        let start = &cell as *const SendCell<[u8; 16]> as usize;
+        let ptr = cell.as_ptr() as usize;
+        assert!(ptr >= start && ptr + 16 <= start + mem::size_of_val(&cell));
+        assert_eq!(format!("{:p}", cell), format!("{:p}", cell.as_ptr()));
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+
+    #[test]
+    fn get_cloned() {
+        let cell = SendCell::new(String::from("a"));
+        assert_eq!(cell.get_cloned(), "a");
+
+        let t = thread::spawn(move || {
+            assert_eq!(cell.try_get_cloned(), None);
+            cell
+        });
+
+        let r = t.join();
+        let cell = r.unwrap();
+        assert_eq!(cell.try_get_cloned().as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn get_copy() {
+        let value = 1;
+        let cell = SendCell::new(&value as *const i32);
+        assert_eq!(cell.get_copy(), &value as *const i32);
+
+        let t = thread::spawn(move || {
+            assert_eq!(cell.try_get_copy(), None);
+            cell
+        });
+
+        let r = t.join();
+        let cell = r.unwrap();
+        assert_eq!(cell.try_get_copy(), Some(&value as *const i32));
+    }
+
+    #[test]
+    fn update() {
+        let mut cell = SendCell::new(vec![1]);
+        cell.update(|mut v| {
+            v.push(2);
+            v
+        });
+        assert_eq!(cell.get(), &[1, 2]);
+
+        let t = thread::spawn(move || {
+            assert_eq!(cell.try_update(|_| unreachable!()), None);
+            cell
+        });
+
+        let r = t.join();
+        let mut cell = r.unwrap();
+        assert_eq!(cell.as_mut_ptr() as *const _, cell.as_ptr());
+        assert_eq!(cell.try_update(|v| v.into_iter().rev().collect()), Some(()));
+        assert_eq!(cell.get(), &[2, 1]);
+    }
+
+    #[test]
+    fn update_panic() {
+        let dropped = Rc::new(Cell::new(0));
+        let mut cell = SendCell::new(Rc::clone(&dropped));
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            cell.update(|v| {
+                v.set(1);
+                panic!("consumed");
+            })
+        }));
+        assert!(res.is_err());
+        // The value was dropped while unwinding
+        assert_eq!(Rc::strong_count(&dropped), 1);
+
+        assert!(cell.is_poisoned());
+        cell.clear_poison();
+        assert!(cell.is_poisoned());
+        assert!(cell.try_get().is_none());
+        let cell = cell.try_into_inner().unwrap_err();
+        drop(cell);
+        assert_eq!(dropped.get(), 1);
+    }
+
+    #[test]
+    fn expect_success() {
+        let cell = SendCell::new(1);
+        assert_eq!(cell.get_expect("value"), &1);
+        assert_eq!(*cell.borrow_expect("value"), 1);
+        assert_eq!(cell.into_inner_expect("value"), 1);
+    }
+
+    #[test]
+    fn expect_failure() {
+        let t = thread::spawn(move || SendCell::new(1));
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        let r = panic::catch_unwind(|| {
+            cell.get_expect("audio device");
+        });
+        let err = r.unwrap_err();
+        let violation = err.downcast_ref::<SendCellViolation>().unwrap();
+        assert_eq!(violation.kind(), ViolationKind::Access);
+        assert_eq!(violation.message(), Some("audio device"));
+        assert!(violation.to_string().starts_with("audio device: "));
+    }
+
+    #[test]
+    fn borrow_success() {
+        let cell = SendCell::new(1);
+        assert_eq!(*cell.borrow(), 1);
+        assert_eq!(*cell.try_borrow().unwrap(), 1);
+    }
+
+    #[test]
+    fn ref_as_deref() {
+        let cell = SendCell::new(std::rc::Rc::new(String::from("abc")));
+        let r: Ref<String> = Ref::as_deref(cell.borrow());
+        assert_eq!(r.as_str(), "abc");
+        let r: Ref<str> = Ref::as_deref(r);
+        assert_eq!(&*r, "abc");
+    }
+
+    #[test]
+    fn ref_clone_display_cloned() {
+        let cell = SendCell::new(String::from("abc"));
+        let r = cell.borrow();
+        let r2 = r.clone();
+        assert_eq!(format!("{}", r2), "abc");
+        assert_eq!(r.cloned(), String::from("abc"));
+    }
+
+    #[test]
+    fn borrow_checked() {
+        let cell = SendCell::new(String::from("abc"));
+        let r = cell.borrow_checked();
+        assert_eq!(format!("{}", r.clone()), "abc");
+        assert_eq!(r.len(), 3);
+
+        thread::scope(|s| {
+            let r = s
+                .spawn(move || {
+                    assert!(r.try_get().is_none());
+                    r.len()
+                })
+                .join()
+                .unwrap_err();
+            assert!(r.downcast_ref::<SendCellViolation>().is_some());
+        });
+
+        let t = thread::spawn(move || {
+            assert!(cell.try_borrow_checked().is_none());
+            cell
+        });
+        assert_eq!(t.join().unwrap().borrow_checked().as_str(), "abc");
+
+        let cell = SendCell::new_with_validator(Cell::new(1), |v| v.get() > 0);
+        let r = cell.borrow_checked();
+        r.set(0);
+        assert!(r.try_get().is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn borrow_failure() {
+        let t = thread::spawn(move || {
+            let cell = SendCell::new(1);
+            assert_eq!(*cell.borrow(), 1);
+            cell
+        });
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        // The cell is dropped while unwinding, which leaks it instead of panicking again
+        cell.borrow();
+    }
+
+    #[test]
+    fn try_borrow_failure() {
+        let t = thread::spawn(move || {
+            let cell = SendCell::new(1);
+            assert_eq!(*cell.borrow(), 1);
+            cell
+        });
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        assert_eq!(cell.try_borrow(), None);
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+
+    #[test]
+    fn into_inner_success() {
+        let cell = SendCell::new(1);
+        assert_eq!(cell.try_into_inner().unwrap(), 1);
+    }
+
+    #[test]
+    fn into_inner_failure() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (back_sender, back_receiver) = std::sync::mpsc::channel();
+        let t = thread::spawn(move || {
+            sender.send(SendCell::new(Rc::new(1))).unwrap();
+            sender.send(SendCell::new(Rc::new(2))).unwrap();
+            // Dropped on this thread after the failed attempt
+            let cell: SendCell<Rc<i32>> = back_receiver.recv().unwrap();
+            assert_eq!(*cell.into_inner(), 2);
+        });
+
+        // The value is leaked as the `SendCell` is dropped while panicking
+        let cell = receiver.recv().unwrap();
+        let r = panic::catch_unwind(panic::AssertUnwindSafe(move || cell.into_inner()));
+        let err = r.unwrap_err();
+        let violation = err.downcast_ref::<SendCellViolation>().unwrap();
+        assert_eq!(violation.kind(), ViolationKind::Access);
+
+        let cell = receiver.recv().unwrap().try_into_inner().unwrap_err();
+        back_sender.send(cell).unwrap();
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn fragile_roundtrip() {
+        let cell = SendCell::<i32>::from(fragile::Fragile::new(1));
+        assert_eq!(cell.get(), &1);
+        let fragile = cell.into_fragile();
+        assert_eq!(fragile.get(), &1);
+    }
+
+    #[test]
+    fn fragile_roundtrip_keeps_binding() {
+        let t = thread::spawn(move || SendCell::new(1).into_fragile());
+
+        let r = t.join();
+        let cell = SendCell::<i32>::from(r.unwrap());
+
+        assert_eq!(cell.try_get(), None);
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+
+    #[test]
+    fn fragile_roundtrip_other_thread() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (back_sender, back_receiver) = std::sync::mpsc::channel();
+        let t = thread::spawn(move || {
+            sender
+                .send((SendCell::new(1), fragile::Fragile::new(2)))
+                .unwrap();
+            let (cell, fragile): (SendCell<i32>, fragile::Fragile<i32>) =
+                back_receiver.recv().unwrap();
+            assert_eq!(cell.get(), &1);
+            let cell = SendCell::<i32>::from(fragile);
+            assert_eq!(cell.get(), &2);
+            assert!(cell.thread_token().is_current());
+        });
+
+        // A `Fragile` for the value can only be created on its thread
+        let (cell, fragile) = receiver.recv().unwrap();
+        let cell = cell.try_into_fragile().unwrap_err();
+
+        // Both conversions happen on this thread and keep the binding
+        let converted = SendCell::<i32>::from(fragile);
+        assert_eq!(converted.try_get(), None);
+        assert!(!converted.thread_token().is_current());
+        assert_eq!(
+            format!("{:?}", converted),
+            "SendCell(<bound to unknown thread>)"
+        );
+        let fragile = converted.into_fragile();
+        assert!(fragile.try_get().is_err());
+
+        back_sender.send((cell, fragile)).unwrap();
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn try_into_fragile_failure() {
+        let token = thread::spawn(ThreadToken::current).join().unwrap();
+        let cell = SendCell::new_bound_to(1, token);
+
+        let res = cell.try_into_fragile();
+        assert!(res.is_err());
+        // Forget so drop() is not run, which would panic
+        mem::forget(res);
+    }
+
+    #[cfg(feature = "send_wrapper")]
+    #[test]
+    fn send_wrapper_roundtrip() {
+        let cell = SendCell::from_send_wrapper(send_wrapper::SendWrapper::new(1));
+        assert_eq!(cell.location().line(), line!() - 1);
+        assert_eq!(cell.get(), &1);
+        let wrapper = cell.try_into_send_wrapper().unwrap();
+        assert_eq!(*wrapper, 1);
+    }
+
+    #[cfg(feature = "send_wrapper")]
+    #[test]
+    fn try_from_send_wrapper_failure() {
+        let t = thread::spawn(move || send_wrapper::SendWrapper::new(1));
+
+        let r = t.join();
+        let wrapper = r.unwrap();
+
+        let res = SendCell::try_from_send_wrapper(wrapper);
+        assert!(res.is_err());
+        // Forget so drop() is not run, which would panic
+        mem::forget(res);
+    }
+
+    #[test]
+    fn to_send_snapshot_success() {
+        let cell = SendCell::new(vec![1, 2, 3]);
+        assert_eq!(cell.to_send_snapshot(|v| v.len()), 3);
+        assert_eq!(cell.try_to_send_snapshot(|v| v[0]), Some(1));
+    }
+
+    #[test]
+    fn try_to_send_snapshot_failure() {
+        let t = thread::spawn(move || SendCell::new(vec![1, 2, 3]));
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        assert_eq!(cell.try_to_send_snapshot(|v| v.len()), None);
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+
+    #[test]
+    fn inspect() {
+        let cell = SendCell::new(1);
+        let mut seen = Vec::new();
+        let value = cell.inspect(|v| seen.push(*v)).get_copy();
+        assert_eq!(value, 1);
+        assert_eq!(seen, [1]);
+
+        let t = thread::spawn(move || {
+            assert!(cell.try_inspect(|_| unreachable!()).is_none());
+            cell
+        });
+
+        let r = t.join();
+        let cell = r.unwrap();
+        assert!(cell.try_inspect(|v| assert_eq!(*v, 1)).is_some());
+    }
+
+    #[test]
+    fn map_send_success() {
+        let cell = SendCell::new((1, "a"));
+        assert_eq!(cell.map_send(|(v, _)| v), 1);
+        let cell = SendCell::new((1, "a"));
+        assert_eq!(cell.try_map_send(|(_, v)| v).unwrap(), "a");
+    }
+
+    #[test]
+    fn try_map_send_failure() {
+        let t = thread::spawn(move || SendCell::new((1, "a")));
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        let res = cell.try_map_send(|(v, _)| v);
+        assert!(res.is_err());
+        // Forget so drop() is not run, which would panic
+        mem::forget(res);
+    }
+
+    #[test]
+    fn thread_token() {
+        let cell = SendCell::new(1);
+        assert!(cell.is_bound_to(&ThreadToken::current()));
+        assert_eq!(cell.thread_token(), ThreadToken::current());
+
+        let t = thread::spawn(move || {
+            assert!(!cell.is_bound_to(&ThreadToken::current()));
+            cell
+        });
+
+        let r = t.join();
+        let cell = r.unwrap();
+        assert!(cell.thread_token().is_current());
+    }
+
+    #[test]
+    fn same_thread() {
+        let cell = SendCell::new(1);
+        let other = SendCell::new("a");
+        assert!(cell.same_thread(&other));
 
-    /// Consumes the `SendCell`, returning the wrapped value if successful.
-    ///
-    /// The wrapped value is returned if this is called from the same thread as the one where the
-    /// original value was created, otherwise the `SendCell` is returned as `Err(self)`.
-    pub fn try_into_inner(self) -> Result<T, Self> {
-        self.value
-            .try_into_inner()
-            .map_err(|v| SendCell { value: v })
-    }
+        let t = thread::spawn(move || {
+            let foreign = SendCell::new(2);
+            assert!(cell.same_thread(&other));
+            assert!(!cell.same_thread(&foreign));
+            cell
+        });
 
-    /// Immutably borrows the wrapped value.
-    ///
-    /// Multiple immutable borrows can be taken out at the same time.
-    ///
-    /// # Panics
-    ///
-    /// Panics if called from a different thread than the one where the original value was created.
-    pub fn get(&self) -> &T {
-        self.value.get()
+        let r = t.join();
+        assert!(r.is_ok());
     }
 
-    /// Tries to immutably borrow the wrapped value.
-    ///
-    /// `None` is returned if called from a different thread than the one where the original value
-    /// was created.
-    ///
-    /// Multiple immutable borrows can be taken out at the same time.
-    pub fn try_get(&self) -> Option<&T> {
-        self.value.try_get().ok()
-    }
+    #[test]
+    fn new_bound_to() {
+        let token = ThreadToken::current();
+        let t = thread::spawn(move || {
+            let cell = SendCell::new_bound_to(1, token);
+            assert_eq!(cell.try_get(), None);
+            cell
+        });
 
-    /// Immutably borrows the wrapped value.
-    ///
-    /// The borrow lasts until the returned `Ref` exits scope. Multiple immutable borrows can be
-    /// taken out at the same time.
-    ///
-    /// # Panics
-    ///
-    /// Panics if called from a different thread than the one where the original value was created.
-    pub fn borrow(&self) -> Ref<T> {
-        Ref { value: self.get() }
+        let r = t.join();
+        let cell = r.unwrap();
+        assert_eq!(cell.get(), &1);
     }
 
-    /// Tries to immutably borrow the wrapped value.
-    ///
-    /// `None` is returned if called from a different thread than the one where the original value
-    /// was created.
-    ///
-    /// The borrow lasts until the returned `Ref` exits scope. Multiple immutable borrows can be
-    /// taken out at the same time.
-    pub fn try_borrow(&self) -> Option<Ref<T>> {
-        self.try_get().map(|value| Ref { value: value })
+    #[test]
+    fn into_parts_success() {
+        let (value, token) = SendCell::new(1).into_parts();
+        let cell = unsafe { SendCell::from_parts(value + 1, token) };
+        assert_eq!(cell.get(), &2);
+        let (value, _) = cell.try_into_parts().unwrap();
+        assert_eq!(value, 2);
     }
-}
 
-impl<T> From<T> for SendCell<T> {
-    fn from(t: T) -> SendCell<T> {
-        SendCell::new(t)
-    }
-}
+    #[test]
+    fn from_parts_keeps_binding() {
+        let t = thread::spawn(move || {
+            let (value, token) = SendCell::new(1).into_parts();
+            let value = value as usize;
+            let (cell, token) = thread::spawn(move || {
+                let cell = unsafe { SendCell::from_parts(value, token) };
+                assert_eq!(cell.try_get(), None);
+                (cell, token)
+            })
+            .join()
+            .unwrap();
+            assert_eq!(cell.get(), &1);
+            token
+        });
 
-impl<T: Default> Default for SendCell<T> {
-    fn default() -> SendCell<T> {
-        SendCell::new(T::default())
-    }
-}
+        let r = t.join();
+        let token = r.unwrap();
+        let cell = unsafe { SendCell::from_parts(1, token) };
 
-impl<T: Clone> Clone for SendCell<T> {
-    fn clone(&self) -> SendCell<T> {
-        SendCell::new(self.get().clone())
+        let res = cell.try_into_parts();
+        assert!(res.is_err());
+        // Forget so drop() is not run, which would panic
+        mem::forget(res);
     }
-}
 
-impl<T: fmt::Debug> fmt::Debug for SendCell<T> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        self.get().fmt(f)
+    #[test]
+    fn zip_success() {
+        let cell = SendCell::new(1).zip(SendCell::new("a"));
+        assert_eq!(cell.get(), &(1, "a"));
+        let cell = SendCell::new(1).try_zip(SendCell::new("a")).unwrap();
+        assert_eq!(cell.get(), &(1, "a"));
     }
-}
 
-impl<T: PartialEq> PartialEq<SendCell<T>> for SendCell<T> {
-    fn eq(&self, other: &Self) -> bool {
-        self.get().eq(other.get())
+    #[test]
+    fn try_zip_failure() {
+        let t = thread::spawn(move || SendCell::new(1));
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        let res = SendCell::new(2).try_zip(cell);
+        assert!(res.is_err());
+        let (cell, other) = res.unwrap_err();
+        assert_eq!(cell.get(), &2);
+        // Forget so drop() is not run, which would panic
+        mem::forget(other);
     }
-}
-impl<T: Eq> Eq for SendCell<T> {}
 
-impl<T: PartialOrd> PartialOrd<SendCell<T>> for SendCell<T> {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        self.get().partial_cmp(other.get())
+    #[test]
+    fn unzip_success() {
+        let (a, b) = SendCell::new((1, "a")).unzip();
+        assert_eq!(a.get(), &1);
+        assert_eq!(b.get(), &"a");
+        let (a, b) = SendCell::new((1, "a")).try_unzip().unwrap();
+        assert_eq!(a.get(), &1);
+        assert_eq!(b.get(), &"a");
     }
-}
-impl<T: Ord> Ord for SendCell<T> {
-    fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.get().cmp(other.get())
+
+    #[test]
+    fn try_unzip_failure() {
+        let t = thread::spawn(move || SendCell::new((1, "a")));
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        let res = cell.try_unzip();
+        assert!(res.is_err());
+        // Forget so drop() is not run, which would panic
+        mem::forget(res);
     }
-}
 
-impl<T: Hash> Hash for SendCell<T> {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.get().hash(state)
+    #[test]
+    fn transpose_option_success() {
+        let cell = SendCell::new(Some(1)).transpose().unwrap();
+        assert_eq!(cell.get(), &1);
+        assert!(SendCell::new(None::<i32>).transpose().is_none());
+        let cell = SendCell::new(Some(1)).try_transpose().unwrap().unwrap();
+        assert_eq!(cell.get(), &1);
     }
-}
 
-unsafe impl<T> Send for SendCell<T> {}
+    #[test]
+    fn get_or_insert_with_success() {
+        let mut cell = SendCell::new(None);
+        assert_eq!(cell.get_or_insert_with(|| 1), &mut 1);
+        assert_eq!(cell.get_or_insert_with(|| 2), &mut 1);
+        assert_eq!(cell.try_get_or_insert_with(|| 3), Some(&mut 1));
+    }
 
-/// Wraps a borrowed reference to a value in a `SendCell` box.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Ref<'a, T: 'a> {
-    value: &'a T,
-}
+    #[test]
+    fn try_get_or_insert_with_failure() {
+        let t = thread::spawn(move || SendCell::new(None));
 
-impl<'a, T: 'a> ops::Deref for Ref<'a, T> {
-    type Target = T;
+        let r = t.join();
+        let mut cell = r.unwrap();
 
-    fn deref(&self) -> &T {
-        self.value
+        assert_eq!(cell.try_get_or_insert_with(|| 1), None);
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::mem;
-    use std::panic;
-    use std::thread;
+    #[test]
+    fn try_transpose_option_failure() {
+        let t = thread::spawn(move || SendCell::new(Some(1)));
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        let res = cell.try_transpose();
+        assert!(res.is_err());
+        // Forget so drop() is not run, which would panic
+        mem::forget(res);
+    }
 
     #[test]
-    fn get_success() {
-        let cell = SendCell::new(1);
+    fn transpose_result_success() {
+        let cell = SendCell::new(Ok::<_, ()>(1)).transpose().unwrap();
+        assert_eq!(cell.get(), &1);
+        assert_eq!(SendCell::new(Err::<i32, _>(2)).transpose().err(), Some(2));
+        let cell = SendCell::new(Ok::<_, ()>(1))
+            .try_transpose()
+            .unwrap()
+            .unwrap();
         assert_eq!(cell.get(), &1);
-        assert_eq!(cell.try_get(), Some(&1));
     }
 
     #[test]
-    #[should_panic]
-    fn get_failure() {
-        let t = thread::spawn(move || {
-            let cell = SendCell::new(1);
-            assert_eq!(cell.get(), &1);
-            cell
-        });
+    fn try_transpose_result_failure() {
+        let t = thread::spawn(move || SendCell::new(Ok::<_, ()>(1)));
 
         let r = t.join();
         let cell = r.unwrap();
 
-        // Some dance here to
-        // a) Not panic uncontrolled: this would run the Drop impl
-        //    of SendCell from the wrong thread, which panics again
-        // b) Make the borrow checker happy so that we can forget
-        //    the cell in case of panic (and don't have it borrowed anymore)
-        // c) And then rethrow the panic
-        let panic = {
-            let res = panic::catch_unwind(panic::AssertUnwindSafe(|| cell.get()));
+        let res = cell.try_transpose();
+        assert!(res.is_err());
+        // Forget so drop() is not run, which would panic
+        mem::forget(res);
+    }
 
-            res.err()
-        };
-        mem::forget(cell);
-        if let Some(payload) = panic {
-            panic::resume_unwind(payload);
-        }
+    #[test]
+    fn flatten_success() {
+        let cell = SendCell::new(SendCell::new(1)).flatten();
+        assert_eq!(cell.get(), &1);
+        let cell = SendCell::new(SendCell::new(1)).try_flatten().unwrap();
+        assert_eq!(cell.get(), &1);
     }
 
     #[test]
-    fn try_get_failure() {
-        let t = thread::spawn(move || {
-            let cell = SendCell::new(1);
-            assert_eq!(cell.get(), &1);
-            cell
-        });
+    fn try_flatten_failure() {
+        let t = thread::spawn(move || SendCell::new(1));
 
         let r = t.join();
         let cell = r.unwrap();
 
+        let res = SendCell::new(cell).try_flatten();
+        assert!(res.is_err());
+        let cell = res.unwrap_err().into_inner();
         assert_eq!(cell.try_get(), None);
         // Forget so drop() is not run, which would panic
         mem::forget(cell);
     }
 
     #[test]
-    fn borrow_success() {
-        let cell = SendCell::new(1);
-        assert_eq!(*cell.borrow(), 1);
-        assert_eq!(*cell.try_borrow().unwrap(), 1);
+    fn scatter_success() {
+        let cells = SendCell::new(vec![1, 2]).scatter();
+        assert_eq!(cells.len(), 2);
+        assert_eq!(cells[0].get(), &1);
+        assert_eq!(cells[1].get(), &2);
+        assert!(cells[0].same_thread(&cells[1]));
+        let cells = SendCell::new(Vec::<i32>::new()).try_scatter().unwrap();
+        assert!(cells.is_empty());
     }
 
     #[test]
-    #[should_panic]
-    fn borrow_failure() {
-        let t = thread::spawn(move || {
-            let cell = SendCell::new(1);
-            assert_eq!(*cell.borrow(), 1);
-            cell
-        });
+    fn try_scatter_failure() {
+        let t = thread::spawn(move || SendCell::new(vec![1, 2]));
 
         let r = t.join();
         let cell = r.unwrap();
 
-        // Some dance here to
-        // a) Not panic uncontrolled: this would run the Drop impl
-        //    of SendCell from the wrong thread, which panics again
-        // b) Make the borrow checker happy so that we can forget
-        //    the cell in case of panic (and don't have it borrowed anymore)
-        // c) And then rethrow the panic
-        let panic = {
-            let res = panic::catch_unwind(panic::AssertUnwindSafe(|| cell.borrow()));
+        let res = cell.try_scatter();
+        assert!(res.is_err());
+        // Forget so drop() is not run, which would panic
+        mem::forget(res);
+    }
 
-            res.err()
-        };
-        mem::forget(cell);
-        if let Some(payload) = panic {
-            panic::resume_unwind(payload);
-        }
+    #[test]
+    fn gather_success() {
+        let cell = SendCell::gather(vec![SendCell::new(1), SendCell::new(2)]).unwrap();
+        assert_eq!(cell.get(), &vec![1, 2]);
+        let cell = SendCell::gather(SendCell::new(vec![3, 4]).scatter()).unwrap();
+        assert_eq!(cell.into_inner(), vec![3, 4]);
     }
 
     #[test]
-    fn try_borrow_failure() {
-        let t = thread::spawn(move || {
-            let cell = SendCell::new(1);
-            assert_eq!(*cell.borrow(), 1);
-            cell
-        });
+    fn gather_failure() {
+        let t = thread::spawn(move || SendCell::new(2));
 
         let r = t.join();
-        let cell = r.unwrap();
+        let other = r.unwrap();
 
-        assert_eq!(cell.try_borrow(), None);
+        let err = SendCell::gather(vec![SendCell::new(1), other, SendCell::new(3)]).unwrap_err();
+        assert_eq!(err.index(), 1);
+        assert_eq!(
+            err.error().origin_thread(),
+            err.cells()[1].thread_token().thread_id()
+        );
+        let mut cells = err.into_cells();
+        assert_eq!(cells.len(), 3);
         // Forget so drop() is not run, which would panic
-        mem::forget(cell);
+        mem::forget(cells.remove(1));
     }
 
     #[test]
-    fn into_inner_success() {
-        let cell = SendCell::new(1);
-        assert_eq!(cell.try_into_inner().unwrap(), 1);
+    fn unwind_safe() {
+        fn assert_unwind_safe<T: UnwindSafe + RefUnwindSafe>() {}
+
+        assert_unwind_safe::<SendCell<i32>>();
+        assert_unwind_safe::<SendCell<Vec<String>>>();
+    }
+
+    #[test]
+    fn try_cmp_success() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let a = SendCell::new(1);
+        let b = SendCell::new(2);
+        assert_eq!(a.try_eq(&b), Some(false));
+        assert_eq!(a.try_partial_cmp(&b), Some(Some(cmp::Ordering::Less)));
+        assert_eq!(a.try_cmp(&b), Some(cmp::Ordering::Less));
+
+        let mut hasher = DefaultHasher::new();
+        assert_eq!(a.try_hash(&mut hasher), Some(()));
+        let mut expected = DefaultHasher::new();
+        1.hash(&mut expected);
+        assert_eq!(hasher.finish(), expected.finish());
+    }
+
+    #[test]
+    fn try_cmp_failure() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let t = thread::spawn(move || SendCell::new(1));
+
+        let r = t.join();
+        let a = r.unwrap();
+        let b = SendCell::new(2);
+
+        assert_eq!(a.try_eq(&b), None);
+        assert_eq!(b.try_eq(&a), None);
+        assert_eq!(a.try_partial_cmp(&b), None);
+        assert_eq!(a.try_cmp(&b), None);
+        assert_eq!(a.try_hash(&mut DefaultHasher::new()), None);
+        // Forget so drop() is not run, which would panic
+        mem::forget(a);
+    }
+
+    #[test]
+    fn extend() {
+        let mut cell = SendCell::new(vec![1]);
+        cell.extend(2..4);
+        assert_eq!(cell.get(), &[1, 2, 3]);
+
+        let t = thread::spawn(move || {
+            let r = panic::catch_unwind(panic::AssertUnwindSafe(|| cell.extend(Some(4))));
+            assert!(r.is_err());
+            mem::forget(cell);
+        });
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn hasher() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let mut hasher = DefaultHasher::new();
+        let mut cell = SendCell::new(DefaultHasher::new());
+        (1u32, "a").hash(&mut hasher);
+        (1u32, "a").hash(&mut cell);
+        assert_eq!(cell.finish(), hasher.finish());
+
+        let t = thread::spawn(move || {
+            let r = panic::catch_unwind(panic::AssertUnwindSafe(|| cell.write_u8(1)));
+            assert!(r.is_err());
+        });
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn new_many() {
+        let cells = SendCell::new_many(vec![1, 2, 3]);
+        assert_eq!(cells.len(), 3);
+        assert_eq!(cells[2].get(), &3);
+        assert!(cells.iter().all(|cell| cell.same_thread(&cells[0])));
+        assert_eq!(cells[0].location(), cells[1].location());
     }
 
-    // FIXME: Can't test the failure case of to_inner() as it will
-    // panic and then during unwinding call the Drop impl of SendCell,
-    // which will panic again and can't be handled by the test
+    #[test]
+    fn from_iter() {
+        let cell = (1..4).collect::<SendCell<Vec<_>>>();
+        assert_eq!(cell.get(), &[1, 2, 3]);
+        assert!(cell.thread_token().is_current());
+    }
 
     #[test]
     fn try_into_inner_failure() {
@@ -298,7 +2445,7 @@ mod tests {
         mem::forget(res);
     }
 
-    struct Dummy(i32);
+    struct Dummy(#[allow(dead_code)] i32);
     impl Drop for Dummy {
         fn drop(&mut self) {}
     }
@@ -312,6 +2459,21 @@ mod tests {
         let _ = r.unwrap();
     }
 
+    #[test]
+    fn drop_while_panicking() {
+        let t = thread::spawn(move || SendCell::new(Dummy(1)));
+        let cell = t.join().unwrap();
+
+        let r = panic::catch_unwind(panic::AssertUnwindSafe(move || {
+            let _cell = cell;
+            panic!("original panic");
+        }));
+        assert_eq!(
+            r.unwrap_err().downcast_ref::<&str>(),
+            Some(&"original panic")
+        );
+    }
+
     #[test]
     fn drop_is_not_run_from_other_thread() {
         use std::sync::atomic::{AtomicBool, Ordering};
@@ -331,13 +2493,14 @@ mod tests {
             let _ = v;
         });
         let error = t.join().expect_err("thread should have panicked");
+        let violation = error.downcast_ref::<SendCellViolation>().unwrap();
+        assert_eq!(violation.kind(), ViolationKind::Drop);
         assert_eq!(
-            error.downcast_ref::<&str>(),
-            Some(&"destructor of fragile object ran on wrong thread")
+            violation.to_string(),
+            "destructor of fragile object ran on wrong thread"
         );
-        assert_eq!(
-            is_dropped.load(Ordering::SeqCst),
-            false,
+        assert!(
+            !is_dropped.load(Ordering::SeqCst),
             "Drop impl should not have been executed"
         );
     }
@@ -0,0 +1,46 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use objc2::MainThreadMarker;
+
+use super::SendCell;
+
+impl<T> SendCell<T> {
+    /// Creates a new `SendCell` containing `value` that is bound to the main thread.
+    ///
+    /// The `MainThreadMarker` proves at compile time that this is called from the main thread.
+    pub fn new_on_main_thread(value: T, _mtm: MainThreadMarker) -> Self {
+        SendCell::new(value)
+    }
+
+    /// Returns a `MainThreadMarker` if the `SendCell` is bound to the main thread and this is
+    /// called from the main thread.
+    ///
+    /// `None` is returned otherwise.
+    pub fn main_thread_marker(&self) -> Option<MainThreadMarker> {
+        self.try_get()?;
+        MainThreadMarker::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn main_thread_marker_off_main_thread() {
+        // Tests might run on the main thread with `--test-threads=1`, so use a thread of our own
+        let cell = thread::spawn(|| {
+            let cell = SendCell::new(1);
+            assert!(cell.main_thread_marker().is_none());
+            cell
+        })
+        .join()
+        .unwrap();
+
+        // Bound to another thread
+        assert!(cell.main_thread_marker().is_none());
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+// A channel for sending a single value between threads, which can be received either by polling
+// from a future or by blocking the receiving thread.
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+struct State<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+    closed: bool,
+    canceled: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    cond: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+pub(crate) fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            value: None,
+            waker: None,
+            closed: false,
+            canceled: false,
+        }),
+        cond: Condvar::new(),
+    });
+
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+pub(crate) struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    pub(crate) fn send(self, value: T) {
+        self.shared.lock().value = Some(value);
+        // Wakes up the receiver from `drop()`
+    }
+
+    // Returns `true` if the `Receiver` was dropped and nobody is interested in the value anymore
+    pub(crate) fn is_canceled(&self) -> bool {
+        self.shared.lock().canceled
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.closed = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.shared.cond.notify_all();
+    }
+}
+
+// Receives `Some(value)`, or `None` if the `Sender` was dropped without sending a value.
+pub(crate) struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    pub(crate) fn poll_recv(&mut self, cx: &mut Context) -> Poll<Option<T>> {
+        let mut state = self.shared.lock();
+        if state.closed {
+            Poll::Ready(state.value.take())
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Poll<Option<T>> {
+        let mut state = self.shared.lock();
+        if state.closed {
+            Poll::Ready(state.value.take())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    pub(crate) fn recv(self) -> Option<T> {
+        let mut state = self.shared.lock();
+        while !state.closed {
+            state = self
+                .shared
+                .cond
+                .wait(state)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+        state.value.take()
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.lock().canceled = true;
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::ops;
+
+use super::SendCell;
+
+macro_rules! impl_binary_op {
+    ($($trait:ident, $method:ident;)*) => {
+        $(
+            /// Applies the operator to the wrapped values, returning a new `SendCell` that is bound
+            /// to the current thread.
+            ///
+            /// # Panics
+            ///
+            /// Panics if called from a different thread than the one where the original values
+            /// were created.
+            impl<T: ops::$trait> ops::$trait for SendCell<T> {
+                type Output = SendCell<T::Output>;
+
+                #[track_caller]
+                fn $method(self, rhs: SendCell<T>) -> SendCell<T::Output> {
+                    self.assert_thread();
+                    rhs.assert_thread();
+                    SendCell::new(self.take_inner().$method(rhs.take_inner()))
+                }
+            }
+
+            /// Applies the operator to the wrapped value, returning a new `SendCell` that is bound
+            /// to the current thread.
+            ///
+            /// # Panics
+            ///
+            /// Panics if called from a different thread than the one where the original value was
+            /// created.
+            impl<T: ops::$trait> ops::$trait<T> for SendCell<T> {
+                type Output = SendCell<T::Output>;
+
+                #[track_caller]
+                fn $method(self, rhs: T) -> SendCell<T::Output> {
+                    SendCell::new(self.into_inner().$method(rhs))
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_op! {
+    Add, add;
+    Sub, sub;
+    Mul, mul;
+    Div, div;
+    Rem, rem;
+    BitAnd, bitand;
+    BitOr, bitor;
+    BitXor, bitxor;
+    Shl, shl;
+    Shr, shr;
+}
+
+macro_rules! impl_unary_op {
+    ($($trait:ident, $method:ident;)*) => {
+        $(
+            /// Applies the operator to the wrapped value, returning a new `SendCell` that is bound
+            /// to the current thread.
+            ///
+            /// # Panics
+            ///
+            /// Panics if called from a different thread than the one where the original value was
+            /// created.
+            impl<T: ops::$trait> ops::$trait for SendCell<T> {
+                type Output = SendCell<T::Output>;
+
+                #[track_caller]
+                fn $method(self) -> SendCell<T::Output> {
+                    SendCell::new(self.into_inner().$method())
+                }
+            }
+        )*
+    };
+}
+
+impl_unary_op! {
+    Neg, neg;
+    Not, not;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn binary_ops() {
+        assert_eq!((SendCell::new(1) + SendCell::new(2)).into_inner(), 3);
+        assert_eq!((SendCell::new(5) - 2).into_inner(), 3);
+        assert_eq!((SendCell::new(0b01) | 0b10).into_inner(), 0b11);
+        assert_eq!((SendCell::new(1u8) << SendCell::new(3u8)).into_inner(), 8);
+        assert!((SendCell::new(1) * 2).thread_token().is_current());
+    }
+
+    #[test]
+    fn unary_ops() {
+        assert_eq!((-SendCell::new(1)).into_inner(), -1);
+        assert!((!SendCell::new(false)).into_inner());
+    }
+
+    #[test]
+    fn binary_ops_failure() {
+        let t = thread::spawn(move || SendCell::new(1));
+        let a = t.join().unwrap();
+
+        let r = thread::spawn(move || SendCell::new(2) + a).join();
+        assert!(r.is_err());
+    }
+}
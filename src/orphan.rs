@@ -0,0 +1,526 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::thread;
+
+use super::{thread_token, SendCell};
+
+// The live `SendCell`s bound to a registered thread and whether the thread has exited
+#[derive(Default)]
+struct ThreadCells {
+    live: AtomicUsize,
+    exited: AtomicBool,
+}
+
+impl ThreadCells {
+    fn add(&self) {
+        self.live.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Returns the number of remaining live `SendCell`s. Never goes below zero, as cells that were
+    // bound to the thread before it was registered are not counted.
+    fn remove(&self) -> usize {
+        match self
+            .live
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |live| {
+                live.checked_sub(1)
+            }) {
+            Ok(live) => live - 1,
+            Err(_) => 0,
+        }
+    }
+}
+
+// Registered threads that `SendCell`s can be bound to. Threads that were never registered are
+// never considered exited. Exited threads are removed once no live `SendCell` is bound to them
+// anymore, and `ThreadId`s are never reused.
+fn threads() -> MutexGuard<'static, HashMap<thread::ThreadId, Arc<ThreadCells>>> {
+    static THREADS: OnceLock<Mutex<HashMap<thread::ThreadId, Arc<ThreadCells>>>> = OnceLock::new();
+    THREADS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+fn mark_exited(thread_id: thread::ThreadId, cells: &ThreadCells) {
+    let mut threads = threads();
+    if cells.live.load(Ordering::Relaxed) == 0 {
+        threads.remove(&thread_id);
+    } else {
+        cells.exited.store(true, Ordering::Relaxed);
+    }
+}
+
+// Detects when a thread has exited.
+//
+// Thread-local destructors can still use values bound to the thread, e.g. through a `Rc` that is
+// shared with an orphaned `SendCell`, and run in no guaranteed order relative to other
+// thread-local destructors. The thread is therefore only marked as exited by a pthread key
+// destructor, which runs after all thread-local destructors. It re-arms itself for the number of
+// rounds that POSIX guarantees so that it also runs after the key destructors of other libraries,
+// e.g. the one running the thread-local destructors on some platforms.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios"
+))]
+mod exit_hook {
+    use std::os::raw::{c_int, c_void};
+    use std::sync::{Arc, OnceLock};
+    use std::thread;
+
+    use super::ThreadCells;
+
+    #[cfg(target_os = "linux")]
+    type Key = std::os::raw::c_uint;
+    #[cfg(target_os = "android")]
+    type Key = c_int;
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    type Key = std::os::raw::c_ulong;
+
+    extern "C" {
+        fn pthread_key_create(
+            key: *mut Key,
+            destructor: Option<unsafe extern "C" fn(*mut c_void)>,
+        ) -> c_int;
+        fn pthread_setspecific(key: Key, value: *const c_void) -> c_int;
+    }
+
+    // `PTHREAD_DESTRUCTOR_ITERATIONS` is at least this on all POSIX systems
+    const ROUNDS: u32 = 4;
+
+    #[cfg(test)]
+    pub(super) const DETECTS_EXIT: bool = true;
+
+    struct Exit {
+        thread_id: thread::ThreadId,
+        cells: Arc<ThreadCells>,
+        round: u32,
+    }
+
+    fn key() -> Option<Key> {
+        static KEY: OnceLock<Option<Key>> = OnceLock::new();
+        *KEY.get_or_init(|| {
+            let mut key = 0;
+            // Safety: `key` is valid for writes and `on_exit()` can be called on any thread
+            match unsafe { pthread_key_create(&mut key, Some(on_exit)) } {
+                0 => Some(key),
+                _ => None,
+            }
+        })
+    }
+
+    // Marks the current thread as exited once it has exited. Returns `false` if that's not
+    // possible.
+    pub(super) fn register(thread_id: thread::ThreadId, cells: &Arc<ThreadCells>) -> bool {
+        let key = match key() {
+            Some(key) => key,
+            None => return false,
+        };
+
+        let exit = Box::into_raw(Box::new(Exit {
+            thread_id,
+            cells: cells.clone(),
+            round: 1,
+        }));
+        // Safety: `key` was created above and the `Exit` is freed by `on_exit()`
+        if unsafe { pthread_setspecific(key, exit as *const c_void) } != 0 {
+            // Safety: The `Exit` was not stored
+            drop(unsafe { Box::from_raw(exit) });
+            return false;
+        }
+        true
+    }
+
+    // Safety: Only called by pthread with an `Exit` stored by `register()` or itself
+    unsafe extern "C" fn on_exit(exit: *mut c_void) {
+        let mut exit = Box::from_raw(exit as *mut Exit);
+        if exit.round < ROUNDS {
+            exit.round += 1;
+            let exit = Box::into_raw(exit);
+            // If re-arming fails, the thread is not marked as exited at all
+            if pthread_setspecific(key().unwrap(), exit as *const c_void) != 0 {
+                drop(Box::from_raw(exit));
+            }
+            return;
+        }
+
+        super::mark_exited(exit.thread_id, &exit.cells);
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios"
+)))]
+mod exit_hook {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::ThreadCells;
+
+    // There is no portable way to run code after all thread-local destructors, so threads are
+    // never considered exited
+    #[cfg(test)]
+    pub(super) const DETECTS_EXIT: bool = false;
+
+    pub(super) fn register(_thread_id: thread::ThreadId, _cells: &Arc<ThreadCells>) -> bool {
+        false
+    }
+}
+
+// The current thread, registered when it is first used
+struct CurrentThread {
+    id: thread::ThreadId,
+    cells: Arc<ThreadCells>,
+}
+
+impl CurrentThread {
+    fn register() -> Self {
+        let id = thread::current().id();
+        let cells = Arc::<ThreadCells>::default();
+        if exit_hook::register(id, &cells) {
+            threads().insert(id, cells.clone());
+        }
+        CurrentThread { id, cells }
+    }
+}
+
+thread_local! {
+    static CURRENT: CurrentThread = CurrentThread::register();
+}
+
+// Registers the current thread for detecting when it exits and returns its `ThreadId`. If
+// detecting that is not possible, the thread is never considered exited.
+pub(crate) fn register_current_thread() -> thread::ThreadId {
+    CURRENT
+        .try_with(|current| current.id)
+        .unwrap_or_else(|_| thread_token::current_thread_id())
+}
+
+// Counts a new `SendCell` bound to the current thread and returns the `ThreadId` of the thread
+pub(crate) fn add_current() -> thread::ThreadId {
+    CURRENT
+        .try_with(|current| {
+            current.cells.add();
+            current.id
+        })
+        .unwrap_or_else(|_| {
+            // The thread is exiting, so it is not in the fast path anymore
+            let id = thread_token::current_thread_id();
+            add_other(id);
+            id
+        })
+}
+
+// Counts a new `SendCell` bound to `thread_id`
+pub(crate) fn add(thread_id: thread::ThreadId) {
+    let counted = CURRENT
+        .try_with(|current| {
+            if current.id == thread_id {
+                current.cells.add();
+            }
+            current.id == thread_id
+        })
+        .unwrap_or(false);
+    if !counted {
+        add_other(thread_id);
+    }
+}
+
+// `SendCell`s bound to other threads are only counted if the thread was registered
+fn add_other(thread_id: thread::ThreadId) {
+    if let Some(cells) = threads().get(&thread_id) {
+        cells.add();
+    }
+}
+
+// Stops counting a `SendCell` bound to `thread_id`. Exited threads are forgotten once they have
+// no live `SendCell`s anymore.
+pub(crate) fn remove(thread_id: thread::ThreadId) {
+    let removed = CURRENT
+        .try_with(|current| {
+            if current.id == thread_id {
+                current.cells.remove();
+            }
+            current.id == thread_id
+        })
+        .unwrap_or(false);
+    if removed {
+        return;
+    }
+
+    let mut threads = threads();
+    let pruned = match threads.get(&thread_id) {
+        Some(cells) => cells.remove() == 0 && cells.exited.load(Ordering::Relaxed),
+        None => false,
+    };
+    if pruned {
+        threads.remove(&thread_id);
+    }
+}
+
+/// Marker trait for types that can be destroyed from any thread once their thread has exited
+///
+/// Some values are only bound to their thread while it is running, e.g. because they are
+/// registered with thread-local state, but are just plain memory once the thread is gone. For
+/// such types `SendCell::reclaim_if_orphaned()` allows getting back the value of an orphaned
+/// `SendCell` instead of leaking it.
+///
+/// # Safety
+///
+/// Implementing this trait asserts that using and dropping a value of this type on another thread
+/// is sound after the thread where it was created has exited.
+pub unsafe trait OrphanSafe {}
+
+impl<T> SendCell<T> {
+    /// Returns `true` if the thread this `SendCell` is bound to has exited.
+    ///
+    /// The value of an orphaned `SendCell` can never be accessed or dropped again, so it is
+    /// leaked. This can be called from any thread, e.g. for detecting and reporting such leaks.
+    ///
+    /// A thread is only considered exited once all its thread-local destructors ran. This is
+    /// currently only detected on Linux, Android, macOS and iOS. On other platforms, and for
+    /// threads whose exit can't be detected for other reasons, this always returns `false`. The
+    /// same can happen for `SendCell`s that were bound to a thread only after it exited, e.g. with
+    /// `SendCell::new_bound_to()`.
+    pub fn is_orphaned(&self) -> bool {
+        if !self.is_bound_to_known_thread() || thread_token::current_thread_id() == self.thread_id {
+            return false;
+        }
+
+        threads()
+            .get(&self.thread_id)
+            .is_some_and(|cells| cells.exited.load(Ordering::Relaxed))
+    }
+}
+
+impl<T: OrphanSafe> SendCell<T> {
+    /// Consumes the `SendCell`, returning the wrapped value if the thread it is bound to has
+    /// exited.
+    ///
+    /// This can be called from any thread. If the `SendCell` is not orphaned, it is returned as
+    /// `Err(self)`.
+    pub fn reclaim_if_orphaned(mut self) -> Result<T, Self> {
+        if self.is_orphaned() {
+            // Threads never come back to life and are only considered exited after all their
+            // thread-local destructors ran, so the value is not accessed from its thread anymore
+            self.rebind(thread_token::current_thread_id());
+            Ok(self.into_inner())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Drops the `SendCell` on the current thread if the thread it is bound to has exited.
+    ///
+    /// This can be called from any thread. If the `SendCell` is not orphaned, it is returned as
+    /// `Err(self)`.
+    pub fn drop_if_orphaned(self) -> Result<(), Self> {
+        self.reclaim_if_orphaned().map(drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::mem;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+    use TransferSafe;
+
+    #[test]
+    fn is_orphaned() {
+        let cell = SendCell::new(Rc::new(1));
+        assert!(!cell.is_orphaned());
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (done_sender, done_receiver) = std::sync::mpsc::channel::<()>();
+        let t = thread::spawn(move || {
+            sender.send(SendCell::new(Rc::new(2))).unwrap();
+            let _ = done_receiver.recv();
+        });
+
+        let other = receiver.recv().unwrap();
+        assert!(!other.is_orphaned());
+        drop(done_sender);
+        t.join().unwrap();
+        assert_eq!(other.is_orphaned(), exit_hook::DETECTS_EXIT);
+
+        // The value can't be dropped anymore
+        mem::forget(other);
+    }
+
+    #[test]
+    fn rebound_to_thread_without_cells() {
+        struct Handle(Rc<u32>);
+
+        unsafe impl TransferSafe for Handle {}
+
+        let cell = thread::spawn(|| SendCell::new(Handle(Rc::new(1))))
+            .join()
+            .unwrap();
+
+        // The thread never creates a `SendCell` itself
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (done_sender, done_receiver) = std::sync::mpsc::channel::<()>();
+        let t = thread::spawn(move || {
+            let cell = cell.transfer_to_current_thread();
+            assert_eq!(*cell.get().0, 1);
+            sender.send(cell).unwrap();
+            let _ = done_receiver.recv();
+        });
+
+        let cell = receiver.recv().unwrap();
+        assert!(!cell.is_orphaned());
+        drop(done_sender);
+        t.join().unwrap();
+        assert_eq!(cell.is_orphaned(), exit_hook::DETECTS_EXIT);
+
+        mem::forget(cell);
+    }
+
+    #[test]
+    fn reclaim_if_orphaned() {
+        struct Buffer(Rc<Vec<u8>>);
+
+        unsafe impl OrphanSafe for Buffer {}
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (done_sender, done_receiver) = std::sync::mpsc::channel::<()>();
+        let t = thread::spawn(move || {
+            sender
+                .send(SendCell::new(Buffer(Rc::new(vec![1]))))
+                .unwrap();
+            let _ = done_receiver.recv();
+        });
+
+        let cell = receiver.recv().unwrap();
+        let cell = cell.reclaim_if_orphaned().err().unwrap();
+        drop(done_sender);
+        t.join().unwrap();
+
+        if exit_hook::DETECTS_EXIT {
+            let buffer = cell.reclaim_if_orphaned().ok().unwrap();
+            assert_eq!(*buffer.0, vec![1]);
+        } else {
+            mem::forget(cell);
+        }
+        assert!(SendCell::new(Buffer(Rc::new(vec![])))
+            .drop_if_orphaned()
+            .is_err());
+    }
+
+    #[test]
+    fn reclaim_from_live_thread_without_cells() {
+        struct Buffer(Rc<Vec<u8>>);
+
+        unsafe impl OrphanSafe for Buffer {}
+        unsafe impl TransferSafe for Buffer {}
+
+        let cell = SendCell::new(Buffer(Rc::new(vec![1])));
+
+        // The thread never creates a `SendCell` itself but keeps using the value
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (done_sender, done_receiver) = std::sync::mpsc::channel::<()>();
+        let t = thread::spawn(move || {
+            let cell = cell.transfer_to_current_thread();
+            let buffer = cell.get().0.clone();
+            sender.send(cell).unwrap();
+            let _ = done_receiver.recv();
+            assert_eq!(*buffer, vec![1]);
+        });
+
+        let cell = receiver.recv().unwrap();
+        let cell = cell.reclaim_if_orphaned().err().unwrap();
+        drop(done_sender);
+        t.join().unwrap();
+
+        if exit_hook::DETECTS_EXIT {
+            assert!(cell.drop_if_orphaned().is_ok());
+        } else {
+            mem::forget(cell);
+        }
+    }
+
+    #[test]
+    fn prune_exited_threads() {
+        struct Buffer(Rc<Vec<u8>>);
+
+        unsafe impl OrphanSafe for Buffer {}
+
+        fn is_known(thread_id: thread::ThreadId) -> bool {
+            threads().contains_key(&thread_id)
+        }
+
+        // Without live cells the thread is forgotten right away
+        let thread_id = thread::spawn(|| {
+            drop(SendCell::new(1));
+            thread::current().id()
+        })
+        .join()
+        .unwrap();
+        assert!(!is_known(thread_id));
+
+        let (thread_id, cell) = thread::spawn(|| {
+            let cell = SendCell::new(Buffer(Rc::new(vec![1])));
+            assert_eq!(is_known(thread::current().id()), exit_hook::DETECTS_EXIT);
+            (thread::current().id(), cell)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(is_known(thread_id), exit_hook::DETECTS_EXIT);
+
+        // Forgotten once the last cell was reclaimed
+        if exit_hook::DETECTS_EXIT {
+            assert_eq!(*cell.reclaim_if_orphaned().ok().unwrap().0, vec![1]);
+        } else {
+            mem::forget(cell);
+        }
+        assert!(!is_known(thread_id));
+    }
+
+    #[test]
+    fn thread_local_destructors() {
+        struct OnExit(mpsc::Sender<()>, mpsc::Receiver<()>);
+
+        impl Drop for OnExit {
+            fn drop(&mut self) {
+                self.0.send(()).unwrap();
+                self.1.recv().unwrap();
+            }
+        }
+
+        thread_local! {
+            static ON_EXIT: RefCell<Option<OnExit>> = const { RefCell::new(None) };
+        }
+
+        let (exiting_sender, exiting_receiver) = mpsc::channel();
+        let (resume_sender, resume_receiver) = mpsc::channel();
+        let (sender, receiver) = mpsc::channel();
+        let t = thread::spawn(move || {
+            // Initialized before the thread is registered, so its destructor might run later
+            ON_EXIT.with(|on_exit| {
+                *on_exit.borrow_mut() = Some(OnExit(exiting_sender, resume_receiver));
+            });
+            sender.send(SendCell::new(Rc::new(1))).unwrap();
+        });
+
+        let cell = receiver.recv().unwrap();
+        exiting_receiver.recv().unwrap();
+        assert!(!cell.is_orphaned());
+        resume_sender.send(()).unwrap();
+        t.join().unwrap();
+        assert_eq!(cell.is_orphaned(), exit_hook::DETECTS_EXIT);
+
+        mem::forget(cell);
+    }
+}
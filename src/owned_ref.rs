@@ -0,0 +1,134 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops;
+use std::panic;
+use std::sync::Arc;
+
+use super::{borrows, SendCell, SendCellViolation, ViolationKind};
+
+/// Wraps a borrowed reference to a value in a shared `SendCell` box.
+///
+/// Unlike `Ref` this keeps the `SendCell` alive for as long as the borrow exists and has no
+/// lifetime, which allows storing it in callbacks and other long-lived places.
+///
+/// An `OwnedRef` can only be created on the thread where the value was created and can't be sent
+/// to other threads.
+pub struct OwnedRef<T> {
+    cell: Arc<SendCell<T>>,
+    // Not Send/Sync as the value must only be accessed from the thread where it was created
+    phantom: PhantomData<*const ()>,
+}
+
+impl<T> OwnedRef<T> {
+    /// Immutably borrows the value wrapped in `cell`.
+    ///
+    /// The borrow lasts until the returned `OwnedRef` exits scope. Multiple immutable borrows can
+    /// be taken out at the same time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    pub fn new(cell: Arc<SendCell<T>>) -> Self {
+        Self::try_new(cell).unwrap_or_else(|cell| {
+            panic::panic_any(SendCellViolation::new(&cell, ViolationKind::Access, None))
+        })
+    }
+
+    /// Tries to immutably borrow the value wrapped in `cell`.
+    ///
+    /// The `OwnedRef` is returned if this is called from the same thread as the one where the
+    /// original value was created, otherwise `cell` is returned as `Err(cell)`.
+    ///
+    /// The borrow lasts until the returned `OwnedRef` exits scope. Multiple immutable borrows can
+    /// be taken out at the same time.
+    pub fn try_new(cell: Arc<SendCell<T>>) -> Result<Self, Arc<SendCell<T>>> {
+        if cell.try_get().is_some() {
+            borrows::acquire(&cell);
+            Ok(OwnedRef {
+                cell,
+                phantom: PhantomData,
+            })
+        } else {
+            Err(cell)
+        }
+    }
+
+    /// Consumes the `OwnedRef`, returning the shared `SendCell`.
+    ///
+    /// This is an associated function that needs to be used as `OwnedRef::into_cell(...)`, so
+    /// that it does not interfere with methods of the wrapped value.
+    pub fn into_cell(this: Self) -> Arc<SendCell<T>> {
+        this.cell.clone()
+    }
+}
+
+impl<T> Clone for OwnedRef<T> {
+    fn clone(&self) -> OwnedRef<T> {
+        borrows::acquire(&self.cell);
+        OwnedRef {
+            cell: self.cell.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> ops::Deref for OwnedRef<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.cell.get()
+    }
+}
+
+impl<T> Drop for OwnedRef<T> {
+    fn drop(&mut self) {
+        borrows::release(&self.cell);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OwnedRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for OwnedRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        (**self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+    use std::thread;
+
+    #[test]
+    fn new_success() {
+        let cell = Arc::new(SendCell::new(1));
+        let r = OwnedRef::new(cell.clone());
+        drop(cell);
+        assert_eq!(*r, 1);
+        assert_eq!(*r.clone(), 1);
+        let cell = OwnedRef::into_cell(r);
+        assert_eq!(cell.get(), &1);
+    }
+
+    #[test]
+    fn try_new_failure() {
+        let t = thread::spawn(move || Arc::new(SendCell::new(1)));
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        let res = OwnedRef::try_new(cell);
+        assert!(res.is_err());
+        // Forget so drop() is not run, which would panic
+        mem::forget(res);
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Mutex;
+use std::thread;
+
+use super::{thread_token, SendCell};
+
+/// Storage for one value per thread
+///
+/// Values are only removed when the storage is dropped or by `clear_exited()`, so references to
+/// them can be handed out for the lifetime of the storage. Values of other threads than the
+/// dropping one are leaked, and the entries of exited threads are kept until then.
+pub(crate) struct PerThread<T> {
+    // Boxed so that the values don't move when the map is resized
+    values: Mutex<HashMap<thread::ThreadId, SendCell<Box<T>>>>,
+}
+
+impl<T> PerThread<T> {
+    pub(crate) fn new() -> Self {
+        PerThread {
+            values: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn get(&self) -> Option<&T> {
+        let values = self.values.lock().unwrap();
+        let value = values.get(&thread_token::current_thread_id())?.get();
+        // Safety: The boxed value is only dropped together with `self`
+        Some(unsafe { &*(&**value as *const T) })
+    }
+
+    pub(crate) fn get_or_insert_with<F: FnOnce() -> T>(&self, f: F) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+
+        // Not holding the lock while calling `f` so that it can use other `PerThread`s
+        let value = SendCell::new(Box::new(f()));
+        self.values
+            .lock()
+            .unwrap()
+            .entry(thread_token::current_thread_id())
+            .or_insert(value);
+        self.get().unwrap()
+    }
+
+    // Removes the entries of threads that have exited and returns how many were removed. Their
+    // values can't be dropped anymore and are leaked.
+    pub(crate) fn clear_exited(&mut self) -> usize {
+        let values = self.values.get_mut().unwrap_or_else(|err| err.into_inner());
+        let exited = values
+            .iter()
+            .filter(|(_, value)| value.is_orphaned())
+            .map(|(thread_id, _)| *thread_id)
+            .collect::<Vec<_>>();
+        for thread_id in &exited {
+            mem::forget(values.remove(thread_id));
+        }
+        exited.len()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.values.lock().unwrap().len()
+    }
+}
+
+impl<T> Drop for PerThread<T> {
+    fn drop(&mut self) {
+        let values = mem::take(self.values.get_mut().unwrap_or_else(|err| err.into_inner()));
+        for (_, value) in values {
+            if value.try_get().is_none() {
+                mem::forget(value);
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::mem;
+use std::panic;
+use std::sync::{Mutex, OnceLock};
+
+use rayon;
+
+use super::{thread_token, AccessError, SendCell, SendCellViolation, ViolationKind};
+
+/// Lazily initialized thread-bound storage for each worker thread of a rayon thread pool
+///
+/// Each worker thread gets its own value, created by the initialization function the first time
+/// it is accessed from that worker thread, e.g. from inside a `par_iter()` body. The values don't
+/// have to implement `Send` as they're never leaving their worker thread.
+///
+/// The `SendCellPerWorker` uses the thread pool that is current when it is created, i.e. the
+/// global thread pool or the one of `ThreadPool::install()`.
+///
+/// # Warning
+///
+/// When dropped, the values are dropped on their worker threads via `rayon::broadcast()`. The
+/// `SendCellPerWorker` must therefore be dropped in the context of the same thread pool it was
+/// created in. Values that can't be dropped on their worker thread are leaked.
+///
+/// ```
+/// # extern crate rayon;
+/// # extern crate send_cell;
+/// use rayon::prelude::*;
+/// use send_cell::SendCellPerWorker;
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+///
+/// # fn main() {
+/// let counters = SendCellPerWorker::new(|| Rc::new(Cell::new(0)));
+/// (0..100).into_par_iter().for_each(|_| {
+///     counters.with(|counter| counter.set(counter.get() + 1));
+/// });
+/// # }
+/// ```
+pub struct SendCellPerWorker<T> {
+    init: Box<dyn Fn() -> T + Send + Sync>,
+    slots: Box<[OnceLock<SendCell<T>>]>,
+    location: &'static panic::Location<'static>,
+}
+
+impl<T> SendCellPerWorker<T> {
+    /// Creates a new `SendCellPerWorker` for the current thread pool that initializes the value of
+    /// each worker thread with `init`.
+    #[track_caller]
+    pub fn new<F: Fn() -> T + Send + Sync + 'static>(init: F) -> Self {
+        SendCellPerWorker {
+            init: Box::new(init),
+            slots: (0..rayon::current_num_threads())
+                .map(|_| OnceLock::new())
+                .collect(),
+            location: panic::Location::caller(),
+        }
+    }
+
+    /// Calls `f` with the value of the current worker thread, initializing it first if needed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if not called from a worker thread of the thread pool the `SendCellPerWorker` was
+    /// created in.
+    pub fn with<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        let slot = match rayon::current_thread_index().and_then(|index| self.slots.get(index)) {
+            Some(slot) => slot,
+            None => panic::panic_any(
+                SendCellViolation::from_error(
+                    AccessError::for_value(thread_token::unknown_thread_id(), self.location),
+                    ViolationKind::Access,
+                    None,
+                )
+                .with_description("trying to access per-worker value from non-worker thread."),
+            ),
+        };
+        f(slot.get_or_init(|| SendCell::new((self.init)())).get())
+    }
+
+    /// Tries to call `f` with the value of the current worker thread, initializing it first if
+    /// needed.
+    ///
+    /// `None` is returned and `f` is not called if not called from a worker thread of the thread
+    /// pool the `SendCellPerWorker` was created in.
+    pub fn try_with<R, F: FnOnce(&T) -> R>(&self, f: F) -> Option<R> {
+        let slot = self.slots.get(rayon::current_thread_index()?)?;
+        let cell = slot.get_or_init(|| SendCell::new((self.init)()));
+        // The slot might have been initialized by a worker thread of another thread pool with
+        // the same index
+        cell.try_get().map(f)
+    }
+}
+
+impl<T> fmt::Debug for SendCellPerWorker<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("SendCellPerWorker")
+            .field("workers", &self.slots.len())
+            .finish()
+    }
+}
+
+impl<T> Drop for SendCellPerWorker<T> {
+    fn drop(&mut self) {
+        let slots = mem::take(&mut self.slots)
+            .into_vec()
+            .into_iter()
+            .map(|slot| Mutex::new(slot.into_inner()))
+            .collect::<Vec<_>>();
+
+        if slots.iter().any(|slot| slot.lock().unwrap().is_some()) {
+            rayon::broadcast(|ctx| {
+                if let Some(slot) = slots.get(ctx.index()) {
+                    let mut slot = slot.lock().unwrap();
+                    if slot.as_ref().is_some_and(|cell| cell.try_get().is_some()) {
+                        drop(slot.take());
+                    }
+                }
+            });
+        }
+
+        for slot in slots {
+            mem::forget(slot.into_inner().unwrap());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn with_in_par_iter() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let inits = Arc::new(AtomicUsize::new(0));
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        struct CountDrop(Rc<Cell<usize>>, Arc<AtomicUsize>);
+
+        impl Drop for CountDrop {
+            fn drop(&mut self) {
+                self.1.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        pool.install(|| {
+            let inits = inits.clone();
+            let drops = drops.clone();
+            let per_worker = SendCellPerWorker::new(move || {
+                inits.fetch_add(1, Ordering::SeqCst);
+                CountDrop(Rc::new(Cell::new(0)), drops.clone())
+            });
+
+            let sum = (0..100)
+                .into_par_iter()
+                .map(|_| {
+                    per_worker.with(|v| {
+                        v.0.set(v.0.get() + 1);
+                        1
+                    })
+                })
+                .sum::<usize>();
+            assert_eq!(sum, 100);
+            assert!(per_worker.try_with(|_| ()).is_some());
+        });
+
+        let initialized = inits.load(Ordering::SeqCst);
+        assert!((1..=2).contains(&initialized));
+        assert_eq!(drops.load(Ordering::SeqCst), initialized);
+    }
+
+    #[test]
+    fn with_from_non_worker() {
+        let per_worker = SendCellPerWorker::new(|| Rc::new(1));
+        assert!(per_worker.try_with(|v| **v).is_none());
+        let err = thread::spawn(move || per_worker.with(|v| **v))
+            .join()
+            .unwrap_err();
+        let violation = err.downcast_ref::<SendCellViolation>().unwrap();
+        assert_eq!(
+            violation.to_string(),
+            "trying to access per-worker value from non-worker thread."
+        );
+    }
+}
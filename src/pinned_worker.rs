@@ -0,0 +1,149 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::sync::mpsc;
+use std::thread;
+
+use super::{thread_token, ExecutorDropped, HomeExecutor, HomeHandle, SendProxy, ThreadToken};
+
+/// A thread owned by this crate that thread-bound values can be created on
+///
+/// Values are created on the worker thread with `PinnedWorker::create()`, which returns a
+/// `SendProxy` for them. All calls through the `SendProxy` and dropping the value are routed to
+/// the worker thread, so the value does not have to implement `Send`.
+///
+/// ```
+/// use send_cell::PinnedWorker;
+/// use std::rc::Rc;
+///
+/// let worker = PinnedWorker::new();
+/// let proxy = worker.create(|| Rc::new(1)).unwrap();
+///
+/// let r = std::thread::spawn(move || proxy.call(|value| **value + 1))
+///     .join()
+///     .unwrap();
+/// assert_eq!(r, Ok(2));
+/// ```
+///
+/// # Warning
+///
+/// When the `PinnedWorker` is dropped, its thread finishes all calls that were queued already and
+/// then exits. Values that are still referenced by a `SendProxy` at that point are leaked, and
+/// further calls return `Err(ExecutorDropped)`.
+pub struct PinnedWorker {
+    handle: HomeHandle,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl PinnedWorker {
+    /// Spawns a new worker thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread can't be spawned.
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        let thread = thread::Builder::new()
+            .name(String::from("send-cell-pinned-worker"))
+            .spawn(move || {
+                let executor = HomeExecutor::new();
+                sender.send(executor.handle()).unwrap();
+                drop(sender);
+
+                executor.run();
+                executor.pump();
+            })
+            .expect("failed to spawn pinned worker thread");
+
+        PinnedWorker {
+            handle: receiver.recv().unwrap(),
+            thread: Some(thread),
+        }
+    }
+
+    /// Creates a value with `f` on the worker thread and returns a `SendProxy` for it.
+    ///
+    /// This blocks until `f` was run.
+    pub fn create<T, F>(&self, f: F) -> Result<SendProxy<T>, ExecutorDropped>
+    where
+        T: 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let handle = self.handle.clone();
+        self.handle
+            .call(move || SendProxy::with_handle(handle, f()))
+    }
+
+    /// Returns a `HomeHandle` for queueing closures on the worker thread.
+    pub fn handle(&self) -> &HomeHandle {
+        &self.handle
+    }
+
+    /// Returns the `ThreadToken` of the worker thread.
+    pub fn thread_token(&self) -> ThreadToken {
+        self.handle.thread_token()
+    }
+}
+
+impl Default for PinnedWorker {
+    fn default() -> Self {
+        PinnedWorker::new()
+    }
+}
+
+impl fmt::Debug for PinnedWorker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("PinnedWorker")
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+impl Drop for PinnedWorker {
+    fn drop(&mut self) {
+        self.handle.quit();
+
+        if let Some(thread) = self.thread.take() {
+            if thread.thread().id() != thread_token::current_thread_id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn create_and_call() {
+        let worker = PinnedWorker::new();
+        assert!(!worker.thread_token().is_current());
+
+        let proxy = worker
+            .create(|| Rc::new(Cell::new(thread::current().id())))
+            .unwrap();
+        let worker_id = worker.thread_token().thread_id();
+
+        let other = proxy.clone();
+        let r = thread::spawn(move || other.call(|value| value.get()))
+            .join()
+            .unwrap();
+        assert_eq!(r, Ok(worker_id));
+        assert_eq!(proxy.call(|value| value.get()), Ok(worker_id));
+    }
+
+    #[test]
+    fn drop_worker() {
+        let worker = PinnedWorker::new();
+        let proxy = worker.create(|| Rc::new(1)).unwrap();
+        drop(worker);
+
+        assert_eq!(proxy.call(|value| **value), Err(ExecutorDropped));
+    }
+}
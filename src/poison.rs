@@ -0,0 +1,107 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use super::SendCell;
+
+// Poisons a `SendCell` if it is dropped while a closure called with the wrapped value panics
+pub(crate) struct PoisonGuard<'a> {
+    poisoned: &'a AtomicBool,
+    panicking: bool,
+}
+
+impl<'a> PoisonGuard<'a> {
+    pub(crate) fn new(poisoned: &'a AtomicBool) -> Self {
+        PoisonGuard {
+            poisoned,
+            panicking: thread::panicking(),
+        }
+    }
+}
+
+impl<'a> Drop for PoisonGuard<'a> {
+    fn drop(&mut self) {
+        if !self.panicking && thread::panicking() {
+            self.poisoned.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<T> SendCell<T> {
+    /// Returns `true` if a closure called with the wrapped value panicked.
+    ///
+    /// This happens if a closure passed to e.g. `SendCell::inspect()` or
+    /// `SendCell::to_send_snapshot()` panics, which might have left the value in an inconsistent
+    /// state. Similar to `std::sync::Mutex`, all further accesses fail afterwards until
+    /// `SendCell::clear_poison()` is called. The `AccessError` of such accesses reports this via
+    /// `AccessError::is_poisoned()`, and panicking accesses on the thread of the `SendCell` panic
+    /// with a `SendCellViolation` of kind `ViolationKind::Poisoned`.
+    ///
+    /// If a closure passed to `SendCell::update()` panics, no value is left in the `SendCell` and
+    /// it stays poisoned forever.
+    ///
+    /// This can be called from any thread.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Relaxed) || self.value.is_lost()
+    }
+
+    /// Clears the poisoned state of the `SendCell`.
+    ///
+    /// This has no effect if the value was lost because of a panic in `SendCell::update()`.
+    ///
+    /// This can be called from any thread.
+    pub fn clear_poison(&self) {
+        self.poisoned.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+    use {SendCellViolation, ViolationKind};
+
+    #[test]
+    fn poison_on_panic() {
+        let cell = SendCell::new(Rc::new(Cell::new(1)));
+        assert!(!cell.is_poisoned());
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            cell.inspect(|v| {
+                v.set(2);
+                panic!("half-updated");
+            });
+        }));
+        assert!(res.is_err());
+        assert!(cell.is_poisoned());
+        assert!(cell.try_get().is_none());
+
+        let err = cell.checked_get().unwrap_err();
+        assert!(err.is_poisoned());
+        assert!(err.to_string().contains("poisoned"));
+
+        let err = panic::catch_unwind(AssertUnwindSafe(|| {
+            cell.get();
+        }))
+        .unwrap_err();
+        let violation = err.downcast_ref::<SendCellViolation>().unwrap();
+        assert_eq!(violation.kind(), ViolationKind::Poisoned);
+        assert!(violation.error().is_poisoned());
+
+        cell.clear_poison();
+        assert_eq!(cell.get().get(), 2);
+    }
+
+    #[test]
+    fn no_poison_without_panic() {
+        let mut cell = SendCell::new(None);
+        assert!(!cell.to_send_snapshot(|v| v.is_some()));
+        assert_eq!(*cell.get_or_insert_with(|| 1), 1);
+        assert!(!cell.is_poisoned());
+    }
+}
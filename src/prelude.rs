@@ -0,0 +1,34 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+//! Re-exports of the most commonly used types, traits and macros
+//!
+//! ```
+//! use send_cell::prelude::*;
+//! use std::rc::Rc;
+//!
+//! let cells = vec![Rc::new(1), Rc::new(2)].into_iter().send_cells().collect::<Vec<_>>();
+//! assert_eq!(**cells[1].get(), 2);
+//! assert!(cells[0].is_bound_to(&ThreadToken::current()));
+//! ```
+
+pub use super::{
+    AllowListCell, AutoCleanupCell, ScopedSendCell, SendCell, SendFactory, SendGlobal, SendMap,
+    SendNonNull, SendProxy, SendPtr, SendRcRefCell, SendReplicated, SendShared, SendVec,
+    WeakShared,
+};
+
+pub use super::{CheckedRef, OwnedRef, Ref};
+
+pub use super::{OrphanSafe, SendCellIteratorExt, SendCellTuple, TransferSafe};
+
+pub use super::{HomeExecutor, HomeHandle, PinnedWorker, ThreadToken};
+
+pub use send_proxy;
+
+#[cfg(feature = "published")]
+pub use super::PublishedSendCell;
+
+#[cfg(feature = "rayon")]
+pub use super::SendCellPerWorker;
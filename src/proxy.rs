@@ -0,0 +1,274 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+
+use super::{AccessRequest, ExecutorDropped, HomeExecutor, HomeHandle, SendCell};
+
+/// A `Send + Sync` handle to a thread-bound value that forwards calls to the value's thread
+///
+/// Calls are queued on the `HomeExecutor` of the value's thread and their results are returned
+/// to the calling thread once the executor was pumped. Calls from the value's thread itself are
+/// run directly. See `send_proxy!` for generating a type with one forwarding method per method of
+/// the value.
+///
+/// The value is dropped on its thread once the last clone of the `SendProxy` is dropped.
+pub struct SendProxy<T: 'static> {
+    value: Option<Arc<SendCell<RefCell<T>>>>,
+    handle: HomeHandle,
+}
+
+impl<T: 'static> SendProxy<T> {
+    /// Creates a new `SendProxy` for `value`, which lives on the thread of `executor`.
+    pub fn new(executor: &HomeExecutor, value: T) -> Self {
+        SendProxy::with_handle(executor.handle(), value)
+    }
+
+    // Creates a new `SendProxy` for `value` on the thread of `handle`, which has to be the
+    // current thread
+    pub(crate) fn with_handle(handle: HomeHandle, value: T) -> Self {
+        debug_assert!(handle.thread_token().is_current());
+        SendProxy {
+            value: Some(Arc::new(SendCell::new(RefCell::new(value)))),
+            handle,
+        }
+    }
+
+    /// Calls `f` with the value on its thread and waits for the result.
+    ///
+    /// An error is returned if the `HomeExecutor` was dropped before `f` was run.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the value's thread if the value is already borrowed by another call, e.g. if `f`
+    /// calls into the same `SendProxy` again.
+    pub fn call<R, F>(&self, f: F) -> Result<R, ExecutorDropped>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut T) -> R + Send + 'static,
+    {
+        self.request(f).wait()
+    }
+
+    /// Queues a call of `f` with the value on its thread and returns an `AccessRequest` for the
+    /// result.
+    ///
+    /// This is the non-blocking version of `SendProxy::call()`. The result can be awaited, waited
+    /// on or polled, and dropping the `AccessRequest` before `f` was run cancels the call.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the value's thread if the value is already borrowed by another call, e.g. if `f`
+    /// calls into the same `SendProxy` again.
+    pub fn request<R, F>(&self, f: F) -> AccessRequest<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut T) -> R + Send + 'static,
+    {
+        let value = self.value.clone().unwrap();
+        self.handle
+            .request(move || f(&mut value.get().borrow_mut()))
+    }
+}
+
+impl<T: 'static> SendProxy<T> {
+    /// Returns the `HomeHandle` calls are forwarded to.
+    pub fn handle(&self) -> &HomeHandle {
+        &self.handle
+    }
+}
+
+impl<T: 'static> Clone for SendProxy<T> {
+    fn clone(&self) -> Self {
+        SendProxy {
+            value: self.value.clone(),
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+impl<T: 'static> fmt::Debug for SendProxy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("SendProxy")
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+impl<T: 'static> Drop for SendProxy<T> {
+    fn drop(&mut self) {
+        let value = match self.value.take() {
+            Some(value) => value,
+            None => return,
+        };
+
+        if self.handle.thread_token().is_current() {
+            return;
+        }
+
+        // Make sure the value is dropped on its thread if this is the last reference
+        if let Err(job) = self.handle.try_spawn(move || drop(value)) {
+            // The executor is gone, so the value can't be dropped on its thread anymore
+            mem::forget(job);
+        }
+    }
+}
+
+/// Generates a `Send + Sync` proxy type with one forwarding method per method of a thread-bound
+/// type.
+///
+/// Each generated method queues the call on the `HomeExecutor` of the value's thread, waits for
+/// the result and returns it as `Result<_, ExecutorDropped>`. Arguments and return values have to
+/// be `Send + 'static`. The proxy can be cloned and is created with `new(&executor, value)` on the
+/// value's thread.
+///
+/// ```
+/// #[macro_use]
+/// extern crate send_cell;
+///
+/// use send_cell::HomeExecutor;
+/// use std::rc::Rc;
+///
+/// struct Pipeline {
+///     position: u64,
+///     _not_send: Rc<()>,
+/// }
+///
+/// impl Pipeline {
+///     fn play(&self) {}
+///
+///     fn seek(&mut self, pos: u64) -> bool {
+///         self.position = pos;
+///         true
+///     }
+/// }
+///
+/// send_proxy! {
+///     pub trait PipelineCtl for Pipeline {
+///         fn play(&self);
+///         fn seek(&mut self, pos: u64) -> bool;
+///     }
+/// }
+///
+/// # fn main() {
+/// let executor = HomeExecutor::new();
+/// let ctl = PipelineCtl::new(&executor, Pipeline { position: 0, _not_send: Rc::new(()) });
+///
+/// let t = std::thread::spawn(move || {
+///     ctl.play().unwrap();
+///     ctl.seek(10).unwrap()
+/// });
+/// while !t.is_finished() {
+///     executor.pump_timeout(std::time::Duration::from_millis(10));
+/// }
+/// assert!(t.join().unwrap());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! send_proxy {
+    (@ret) => { () };
+    (@ret $ret:ty) => { $ret };
+    (
+        $(#[$meta:meta])*
+        $vis:vis trait $name:ident for $ty:ty {
+            $(
+                $(#[$method_meta:meta])*
+                fn $method:ident(& $(mut)? self $(, $arg:ident : $arg_ty:ty)* $(,)?)
+                    $(-> $ret:ty)?;
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug)]
+        $vis struct $name {
+            proxy: $crate::SendProxy<$ty>,
+        }
+
+        impl $name {
+            /// Creates a new proxy for `value`, which lives on the thread of `executor`.
+            $vis fn new(executor: &$crate::HomeExecutor, value: $ty) -> Self {
+                $name {
+                    proxy: $crate::SendProxy::new(executor, value),
+                }
+            }
+
+            $(
+                $(#[$method_meta])*
+                $vis fn $method(
+                    &self
+                    $(, $arg: $arg_ty)*
+                ) -> ::std::result::Result<
+                    $crate::send_proxy!(@ret $($ret)?),
+                    $crate::ExecutorDropped,
+                > {
+                    self.proxy.call(move |value| value.$method($($arg),*))
+                }
+            )*
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+    use std::time::Duration;
+
+    struct Counter {
+        count: Rc<u32>,
+    }
+
+    impl Counter {
+        fn get(&self) -> u32 {
+            *self.count
+        }
+
+        fn add(&mut self, n: u32) {
+            self.count = Rc::new(*self.count + n);
+        }
+    }
+
+    send_proxy! {
+        trait CounterProxy for Counter {
+            fn get(&self) -> u32;
+            fn add(&mut self, n: u32);
+        }
+    }
+
+    #[test]
+    fn proxy_calls() {
+        let executor = HomeExecutor::new();
+        let proxy = CounterProxy::new(&executor, Counter { count: Rc::new(0) });
+        proxy.add(1).unwrap();
+
+        let other = proxy.clone();
+        let t = thread::spawn(move || {
+            other.add(2).unwrap();
+            other.get().unwrap()
+        });
+        while !t.is_finished() {
+            executor.pump_timeout(Duration::from_millis(10));
+        }
+        assert_eq!(t.join().unwrap(), 3);
+        // The clone's reference is dropped on this thread
+        executor.pump();
+        assert_eq!(proxy.get(), Ok(3));
+    }
+
+    #[test]
+    fn proxy_executor_dropped() {
+        let executor = HomeExecutor::new();
+        let proxy = SendProxy::new(&executor, Rc::new(1));
+        drop(executor);
+
+        let t = thread::spawn(move || {
+            assert_eq!(proxy.call(|value| **value), Err(ExecutorDropped));
+        });
+        t.join().unwrap();
+    }
+}
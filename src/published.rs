@@ -0,0 +1,216 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+
+use super::SendCell;
+
+/// A `SendCell` that publishes `Send + Sync` snapshots of its value
+///
+/// The value stays bound to the thread where it was created and is the source of truth. Whenever
+/// the origin thread calls `PublishedSendCell::publish()` or `PublishedSendCell::update()`, a new
+/// snapshot is derived from the value and can afterwards be read from any thread with
+/// `PublishedSendCell::snapshot()`. Reading a snapshot is lock-free, only clones an `Arc` and never
+/// waits for the origin thread or for other readers. Other threads can also subscribe to new
+/// snapshots with `PublishedSendCell::watch()`.
+///
+/// # Warning
+///
+/// Dropping a `PublishedSendCell` from a different thread than the one where the value was created
+/// results in a panic, same as dropping a `SendCell` from a different thread.
+///
+/// ```
+/// use send_cell::PublishedSendCell;
+/// use std::cell::Cell;
+///
+/// let cell = PublishedSendCell::new(Cell::new(1), |v| v.get());
+/// cell.update(|v| v.set(2));
+///
+/// std::thread::scope(|s| {
+///     s.spawn(|| assert_eq!(*cell.snapshot(), 2));
+/// });
+/// ```
+pub struct PublishedSendCell<T, S> {
+    source: SendCell<Source<T, S>>,
+    snapshot: ArcSwap<S>,
+    watchers: Mutex<Vec<mpsc::Sender<Arc<S>>>>,
+}
+
+struct Source<T, S> {
+    value: T,
+    derive: Box<dyn Fn(&T) -> S>,
+}
+
+impl<T, S: Send + Sync> PublishedSendCell<T, S> {
+    /// Creates a new `PublishedSendCell` containing `value` that derives snapshots with `derive`.
+    ///
+    /// The first snapshot is derived immediately.
+    pub fn new<F: Fn(&T) -> S + 'static>(value: T, derive: F) -> Self {
+        let snapshot = Arc::new(derive(&value));
+
+        PublishedSendCell {
+            source: SendCell::new(Source {
+                value,
+                derive: Box::new(derive),
+            }),
+            snapshot: ArcSwap::new(snapshot),
+            watchers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Immutably borrows the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    pub fn get(&self) -> &T {
+        &self.source.get().value
+    }
+
+    /// Tries to immutably borrow the wrapped value.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created.
+    pub fn try_get(&self) -> Option<&T> {
+        self.source.try_get().map(|source| &source.value)
+    }
+
+    /// Derives a new snapshot from the wrapped value and publishes it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    pub fn publish(&self) {
+        self.update(|_| ())
+    }
+
+    /// Tries to derive a new snapshot from the wrapped value and publish it.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created.
+    pub fn try_publish(&self) -> Option<()> {
+        self.try_update(|_| ())
+    }
+
+    /// Calls `f` with the wrapped value and publishes a new snapshot afterwards.
+    ///
+    /// This is meant to be used for values with interior mutability.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    pub fn update<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        let source = self.source.get();
+        let res = f(&source.value);
+        self.store(source);
+        res
+    }
+
+    /// Tries to call `f` with the wrapped value and publishes a new snapshot afterwards.
+    ///
+    /// `None` is returned and `f` is not called if called from a different thread than the one
+    /// where the original value was created.
+    pub fn try_update<R, F: FnOnce(&T) -> R>(&self, f: F) -> Option<R> {
+        let source = self.source.try_get()?;
+        let res = f(&source.value);
+        self.store(source);
+        Some(res)
+    }
+
+    /// Returns the latest published snapshot.
+    ///
+    /// This can be called from any thread and never blocks.
+    pub fn snapshot(&self) -> Arc<S> {
+        self.snapshot.load_full()
+    }
+
+    /// Returns a receiver that gets every snapshot published from now on.
+    ///
+    /// The receiver does not get the current snapshot, use `PublishedSendCell::snapshot()` for
+    /// that. Dropping the receiver unsubscribes it again.
+    ///
+    /// This can be called from any thread.
+    pub fn watch(&self) -> mpsc::Receiver<Arc<S>> {
+        let (sender, receiver) = mpsc::channel();
+        self.watchers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    fn store(&self, source: &Source<T, S>) {
+        let snapshot = Arc::new((source.derive)(&source.value));
+        self.snapshot.store(snapshot.clone());
+        self.watchers
+            .lock()
+            .unwrap()
+            .retain(|watcher| watcher.send(snapshot.clone()).is_ok());
+    }
+}
+
+impl<T, S: fmt::Debug> fmt::Debug for PublishedSendCell<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("PublishedSendCell")
+            .field("snapshot", &**self.snapshot.load())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn publish_and_read_snapshot() {
+        let cell = PublishedSendCell::new(Rc::new(RefCell::new(vec![1])), |v| v.borrow().len());
+        assert_eq!(*cell.snapshot(), 1);
+
+        cell.get().borrow_mut().push(2);
+        assert_eq!(*cell.snapshot(), 1);
+        cell.publish();
+        assert_eq!(*cell.snapshot(), 2);
+
+        assert_eq!(cell.update(|v| v.borrow_mut().pop()), Some(2));
+        assert_eq!(*cell.snapshot(), 1);
+    }
+
+    #[test]
+    fn snapshot_from_other_thread() {
+        let cell = PublishedSendCell::new(Rc::new(1), |v| **v + 1);
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                assert_eq!(*cell.snapshot(), 2);
+                assert!(cell.try_get().is_none());
+                assert_eq!(cell.try_publish(), None);
+                assert_eq!(cell.try_update(|_| ()), None);
+            });
+        });
+
+        assert_eq!(cell.try_publish(), Some(()));
+    }
+
+    #[test]
+    fn watch_from_other_thread() {
+        let cell = PublishedSendCell::new(Rc::new(RefCell::new(0)), |v| *v.borrow());
+        let receiver = cell.watch();
+        let dropped = cell.watch();
+        drop(dropped);
+
+        let t = thread::spawn(move || receiver.iter().take(2).map(|v| *v).collect::<Vec<_>>());
+
+        cell.update(|v| *v.borrow_mut() = 1);
+        cell.update(|v| *v.borrow_mut() = 2);
+        assert_eq!(t.join().unwrap(), vec![1, 2]);
+        assert_eq!(cell.watchers.lock().unwrap().len(), 1);
+
+        cell.publish();
+        assert_eq!(cell.watchers.lock().unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::cell::{self, RefCell};
+use std::error;
+use std::fmt;
+use std::rc::Rc;
+
+use super::SendCell;
+
+/// A `SendCell` around a `Rc<RefCell<T>>`
+///
+/// This combines the thread check of `SendCell` and the dynamic borrow check of `RefCell` into
+/// single calls that report failures of either check with the same error type.
+pub struct SendRcRefCell<T> {
+    cell: SendCell<Rc<RefCell<T>>>,
+}
+
+impl<T> SendRcRefCell<T> {
+    /// Creates a new `SendRcRefCell` containing `value`.
+    #[track_caller]
+    pub fn new(value: T) -> Self {
+        SendRcRefCell {
+            cell: SendCell::new(Rc::new(RefCell::new(value))),
+        }
+    }
+
+    /// Immutably borrows the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created,
+    /// or if the value is currently mutably borrowed.
+    pub fn borrow(&self) -> cell::Ref<'_, T> {
+        self.cell.get().borrow()
+    }
+
+    /// Tries to immutably borrow the wrapped value.
+    ///
+    /// An error is returned if called from a different thread than the one where the original
+    /// value was created, or if the value is currently mutably borrowed.
+    pub fn try_borrow(&self) -> Result<cell::Ref<'_, T>, BorrowError> {
+        self.cell
+            .try_get()
+            .ok_or(BorrowError::InvalidThread)?
+            .try_borrow()
+            .map_err(|_| BorrowError::AlreadyBorrowed)
+    }
+
+    /// Mutably borrows the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created,
+    /// or if the value is currently borrowed.
+    pub fn borrow_mut(&self) -> cell::RefMut<'_, T> {
+        self.cell.get().borrow_mut()
+    }
+
+    /// Tries to mutably borrow the wrapped value.
+    ///
+    /// An error is returned if called from a different thread than the one where the original
+    /// value was created, or if the value is currently borrowed.
+    pub fn try_borrow_mut(&self) -> Result<cell::RefMut<'_, T>, BorrowError> {
+        self.cell
+            .try_get()
+            .ok_or(BorrowError::InvalidThread)?
+            .try_borrow_mut()
+            .map_err(|_| BorrowError::AlreadyBorrowed)
+    }
+
+    /// Returns a new reference to the wrapped `Rc<RefCell<T>>`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    pub fn clone_rc(&self) -> Rc<RefCell<T>> {
+        self.cell.get().clone()
+    }
+
+    /// Tries to return a new reference to the wrapped `Rc<RefCell<T>>`.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created.
+    pub fn try_clone_rc(&self) -> Option<Rc<RefCell<T>>> {
+        self.cell.try_get().cloned()
+    }
+
+    /// Consumes the `SendRcRefCell`, returning the underlying `SendCell`.
+    pub fn into_send_cell(self) -> SendCell<Rc<RefCell<T>>> {
+        self.cell
+    }
+}
+
+impl<T> From<Rc<RefCell<T>>> for SendRcRefCell<T> {
+    #[track_caller]
+    fn from(rc: Rc<RefCell<T>>) -> SendRcRefCell<T> {
+        SendRcRefCell {
+            cell: SendCell::new(rc),
+        }
+    }
+}
+
+impl<T> From<SendCell<Rc<RefCell<T>>>> for SendRcRefCell<T> {
+    fn from(cell: SendCell<Rc<RefCell<T>>>) -> SendRcRefCell<T> {
+        SendRcRefCell { cell }
+    }
+}
+
+impl<T: Default> Default for SendRcRefCell<T> {
+    #[track_caller]
+    fn default() -> SendRcRefCell<T> {
+        SendRcRefCell::new(T::default())
+    }
+}
+
+impl<T> Clone for SendRcRefCell<T> {
+    fn clone(&self) -> SendRcRefCell<T> {
+        SendRcRefCell::from(self.clone_rc())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SendRcRefCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.cell.fmt(f)
+    }
+}
+
+/// An error returned by `SendRcRefCell::try_borrow` and `SendRcRefCell::try_borrow_mut`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BorrowError {
+    /// The value was accessed from a different thread than the one where it was created.
+    InvalidThread,
+    /// The value is already borrowed in a way that conflicts with the requested borrow.
+    AlreadyBorrowed,
+}
+
+impl fmt::Display for BorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            BorrowError::InvalidThread => {
+                f.write_str("value accessed from a different thread than where it was created")
+            }
+            BorrowError::AlreadyBorrowed => f.write_str("value already borrowed"),
+        }
+    }
+}
+
+impl error::Error for BorrowError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+    use std::thread;
+
+    #[test]
+    fn borrow_success() {
+        let cell = SendRcRefCell::new(1);
+        assert_eq!(*cell.borrow(), 1);
+        *cell.borrow_mut() = 2;
+        assert_eq!(*cell.try_borrow().unwrap(), 2);
+        *cell.try_borrow_mut().unwrap() = 3;
+        assert_eq!(*cell.clone_rc().borrow(), 3);
+    }
+
+    #[test]
+    fn location() {
+        let cell = SendRcRefCell::new(1);
+        assert_eq!(cell.into_send_cell().location().line(), line!() - 1);
+        let cell = SendRcRefCell::from(Rc::new(RefCell::new(1)));
+        assert_eq!(cell.into_send_cell().location().line(), line!() - 1);
+    }
+
+    #[test]
+    fn try_borrow_already_borrowed() {
+        let cell = SendRcRefCell::new(1);
+        let r = cell.borrow();
+        assert_eq!(
+            cell.try_borrow_mut().err(),
+            Some(BorrowError::AlreadyBorrowed)
+        );
+        drop(r);
+
+        let r = cell.borrow_mut();
+        assert_eq!(cell.try_borrow().err(), Some(BorrowError::AlreadyBorrowed));
+        drop(r);
+    }
+
+    #[test]
+    fn try_borrow_failure() {
+        let t = thread::spawn(move || SendRcRefCell::new(1));
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        assert_eq!(cell.try_borrow().err(), Some(BorrowError::InvalidThread));
+        assert_eq!(
+            cell.try_borrow_mut().err(),
+            Some(BorrowError::InvalidThread)
+        );
+        assert!(cell.try_clone_rc().is_none());
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+}
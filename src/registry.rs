@@ -0,0 +1,161 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use super::SendShared;
+
+/// A registry of thread-bound values by name
+///
+/// The thread that owns a value can publish a `SendShared` handle to it under a string key, and
+/// other threads can look it up by key and type. Like with all `SendShared` handles, the value can
+/// only be accessed from its own thread.
+///
+/// # Warning
+///
+/// Dropping the last `SendShared` handle to a value from a different thread than the one where the
+/// value was created results in a panic. This also applies when entries are replaced or when a
+/// `Registry` other than `Registry::global()` is dropped.
+///
+/// ```
+/// use send_cell::{Registry, SendShared};
+/// use std::rc::Rc;
+///
+/// let service = SendShared::new(Rc::new(String::from("service")));
+/// Registry::global().publish("my-service", service.clone()).unwrap();
+///
+/// std::thread::spawn(|| {
+///     let service = Registry::global()
+///         .lookup::<Rc<String>>("my-service")
+///         .unwrap();
+///     assert!(service.try_get().is_none());
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+pub struct Registry {
+    entries: Mutex<BTreeMap<String, Box<dyn Any + Send + Sync>>>,
+}
+
+impl Registry {
+    /// Creates a new, empty `Registry`.
+    pub const fn new() -> Self {
+        Registry {
+            entries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the global `Registry`.
+    pub fn global() -> &'static Registry {
+        static GLOBAL: Registry = Registry::new();
+        &GLOBAL
+    }
+
+    /// Publishes `value` under `key`.
+    ///
+    /// If there already is an entry for `key`, `value` is returned as `Err(value)`.
+    pub fn publish<T: 'static, K: Into<String>>(
+        &self,
+        key: K,
+        value: SendShared<T>,
+    ) -> Result<(), SendShared<T>> {
+        let mut entries = self.entries.lock().unwrap();
+        let key = key.into();
+        if entries.contains_key(&key) {
+            return Err(value);
+        }
+        entries.insert(key, Box::new(value));
+        Ok(())
+    }
+
+    /// Looks up the value published under `key`.
+    ///
+    /// `None` is returned if there is no entry for `key` or if its value is not a `T`.
+    pub fn lookup<T: 'static>(&self, key: &str) -> Option<SendShared<T>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(key)?
+            .downcast_ref::<SendShared<T>>()
+            .cloned()
+    }
+
+    /// Removes the value published under `key` and returns it.
+    ///
+    /// `None` is returned and nothing is removed if there is no entry for `key` or if its value is
+    /// not a `T`.
+    pub fn remove<T: 'static>(&self, key: &str) -> Option<SendShared<T>> {
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.get(key)?.is::<SendShared<T>>() {
+            return None;
+        }
+        entries
+            .remove(key)
+            .and_then(|value| value.downcast::<SendShared<T>>().ok())
+            .map(|value| *value)
+    }
+
+    /// Returns the keys of all published values.
+    pub fn keys(&self) -> Vec<String> {
+        self.entries.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        Registry::new()
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("Registry")
+            .field("keys", &self.keys())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn publish_lookup_remove() {
+        let registry = Registry::new();
+        let value = SendShared::new(Rc::new(1));
+        assert!(registry.publish("a", value.clone()).is_ok());
+        assert!(registry.publish("a", value.clone()).is_err());
+        assert_eq!(registry.keys(), vec![String::from("a")]);
+
+        assert!(registry.lookup::<i32>("a").is_none());
+        assert!(registry.lookup::<Rc<i32>>("b").is_none());
+        let found = registry.lookup::<Rc<i32>>("a").unwrap();
+        assert!(SendShared::ptr_eq(&found, &value));
+
+        assert!(registry.remove::<i32>("a").is_none());
+        assert!(registry.remove::<Rc<i32>>("a").is_some());
+        assert!(registry.keys().is_empty());
+    }
+
+    #[test]
+    fn lookup_from_other_thread() {
+        let registry = Registry::new();
+        let value = SendShared::new(Rc::new(1));
+        registry.publish("a", value.clone()).unwrap();
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                let found = registry.lookup::<Rc<i32>>("a").unwrap();
+                assert!(found.try_get().is_none());
+            });
+        });
+
+        assert_eq!(**registry.lookup::<Rc<i32>>("a").unwrap().get(), 1);
+    }
+}
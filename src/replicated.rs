@@ -0,0 +1,237 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::sync::Arc;
+
+use super::per_thread::PerThread;
+use super::{DispatchError, HomeHandle, SendCell, ThreadToken};
+
+// A clone of the master value on its way to the thread of its replica
+struct Replica<T>(T);
+
+// Safety: The caller of `SendReplicated::new()` guarantees that clones of the master value can be
+// moved to other threads
+unsafe impl<T> Send for Replica<T> {}
+
+/// A value with one replica per thread
+///
+/// The master value stays on the thread where the `SendReplicated` was created. Every other thread
+/// that accesses the `SendReplicated` gets its own replica, which is created lazily on first access
+/// by cloning the master value on its thread via the `HomeExecutor` of the given `HomeHandle`.
+/// This allows read-heavy usage of cloneable values that do not implement `Send`, like parsers or
+/// caches, from any thread without ever panicking.
+///
+/// # Warning
+///
+/// Each replica is only dropped if the `SendReplicated` is dropped on the thread of that replica.
+/// Replicas of all other threads are leaked. The replicas of exited threads are kept until the
+/// `SendReplicated` is dropped, unless they are removed with `SendReplicated::clear_exited()`. The
+/// master value is dropped on its thread via the `HomeExecutor`, like a `SendCell` with a
+/// dispatcher.
+///
+/// ```
+/// use send_cell::{HomeExecutor, SendReplicated};
+/// use std::cell::RefCell;
+/// use std::marker::PhantomData;
+///
+/// #[derive(Clone)]
+/// struct Cache {
+///     entries: RefCell<Vec<u32>>,
+///     _not_send: PhantomData<*const ()>,
+/// }
+///
+/// let executor = HomeExecutor::new();
+/// let cache = Cache { entries: RefCell::new(vec![1, 2]), _not_send: PhantomData };
+/// // Safety: Clones of `Cache` don't share any state with each other
+/// let replicated = unsafe { SendReplicated::new(cache, executor.handle()) };
+///
+/// std::thread::scope(|s| {
+///     let t = s.spawn(|| replicated.get().entries.borrow().len());
+///     while !t.is_finished() {
+///         executor.pump_timeout(std::time::Duration::from_millis(10));
+///     }
+///     assert_eq!(t.join().unwrap(), 2);
+/// });
+/// ```
+pub struct SendReplicated<T> {
+    master: Arc<SendCell<T>>,
+    replicas: PerThread<T>,
+}
+
+impl<T: Clone + 'static> SendReplicated<T> {
+    /// Creates a new `SendReplicated` with `value` as the master value for the current thread.
+    ///
+    /// Replicas for other threads are cloned from `value` on the current thread via the
+    /// `HomeExecutor` of `handle`, which has to belong to the current thread.
+    ///
+    /// # Safety
+    ///
+    /// Clones of `value` are moved to other threads. They must not share any state with `value` or
+    /// with each other that is bound to a thread, e.g. via an `Rc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` belongs to a different thread.
+    #[track_caller]
+    pub unsafe fn new(value: T, handle: HomeHandle) -> Self {
+        assert!(
+            handle.thread_token().is_current(),
+            "HomeHandle belongs to a different thread"
+        );
+
+        SendReplicated {
+            master: Arc::new(SendCell::builder().dispatcher(handle).build(value)),
+            replicas: PerThread::new(),
+        }
+    }
+
+    /// Immutably borrows the value of the current thread, creating the replica first if needed.
+    ///
+    /// Creating the replica blocks until the master value was cloned on its thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the replica has to be created and the `HomeExecutor` of the master value was
+    /// dropped.
+    pub fn get(&self) -> &T {
+        match self.try_get() {
+            Ok(value) => value,
+            Err(err) => panic!("Can't create replica: {}", err),
+        }
+    }
+
+    /// Tries to immutably borrow the value of the current thread, creating the replica first if
+    /// needed.
+    ///
+    /// Creating the replica blocks until the master value was cloned on its thread. An error is
+    /// returned if that is not possible.
+    pub fn try_get(&self) -> Result<&T, DispatchError> {
+        if let Some(value) = self.master.try_get() {
+            return Ok(value);
+        }
+
+        if let Some(value) = self.replicas.get() {
+            return Ok(value);
+        }
+
+        let replica = self.master.read_with(|master| Replica(master.clone()))?;
+        Ok(self.replicas.get_or_insert_with(|| replica.0))
+    }
+
+    /// Returns a clone of the value of the current thread, creating the replica first if needed.
+    pub fn get_cloned(&self) -> T {
+        self.get().clone()
+    }
+}
+
+impl<T> SendReplicated<T> {
+    /// Returns `true` if called from the thread of the master value.
+    pub fn is_master(&self) -> bool {
+        self.thread_token().is_current()
+    }
+
+    /// Returns the `ThreadToken` of the thread of the master value.
+    ///
+    /// This can be called from any thread.
+    pub fn thread_token(&self) -> ThreadToken {
+        self.master.thread_token()
+    }
+
+    /// Removes the replicas of threads that have exited and returns how many were removed.
+    ///
+    /// The replicas can't be dropped anymore and are leaked, but they don't take up space in the
+    /// `SendReplicated` anymore. Exited threads are detected like for `SendCell::is_orphaned()`.
+    pub fn clear_exited(&mut self) -> usize {
+        self.replicas.clear_exited()
+    }
+}
+
+impl<T> fmt::Debug for SendReplicated<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("SendReplicated")
+            .field("thread_token", &self.thread_token())
+            .field("replicas", &self.replicas.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::marker::PhantomData;
+    use std::mem;
+    use std::thread;
+    use std::time::Duration;
+    use HomeExecutor;
+
+    #[derive(Clone)]
+    struct Parser {
+        state: Cell<i32>,
+        _not_send: PhantomData<*const ()>,
+    }
+
+    impl Parser {
+        fn new(state: i32) -> Self {
+            Parser {
+                state: Cell::new(state),
+                _not_send: PhantomData,
+            }
+        }
+    }
+
+    #[test]
+    fn replicas_per_thread() {
+        let executor = HomeExecutor::new();
+        let mut replicated = unsafe { SendReplicated::new(Parser::new(0), executor.handle()) };
+        assert!(replicated.is_master());
+        replicated.get().state.set(1);
+        assert_eq!(replicated.get_cloned().state.get(), 1);
+
+        thread::scope(|s| {
+            let t = s.spawn(|| {
+                assert!(!replicated.is_master());
+                // Cloned from the current master value
+                assert_eq!(replicated.get().state.get(), 1);
+                replicated.get().state.set(11);
+                assert_eq!(replicated.get().state.get(), 11);
+            });
+            while !t.is_finished() {
+                executor.pump_timeout(Duration::from_millis(10));
+            }
+            t.join().unwrap();
+        });
+
+        assert_eq!(replicated.get().state.get(), 1);
+        assert_eq!(replicated.replicas.len(), 1);
+
+        // The replica of the exited thread is leaked but not kept anymore
+        let cleared = replicated.clear_exited();
+        assert_eq!(replicated.replicas.len(), 1 - cleared);
+    }
+
+    #[test]
+    fn executor_dropped() {
+        let executor = HomeExecutor::new();
+        let replicated = unsafe { SendReplicated::new(String::from("a"), executor.handle()) };
+        drop(executor);
+
+        let t = thread::spawn(move || {
+            assert_eq!(replicated.try_get(), Err(DispatchError::ExecutorDropped));
+            // Forget so drop() is not run, which would panic
+            mem::forget(replicated);
+        });
+        t.join().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "HomeHandle belongs to a different thread")]
+    fn handle_of_other_thread() {
+        let handle = thread::spawn(|| HomeExecutor::new().handle())
+            .join()
+            .unwrap();
+        let _ = unsafe { SendReplicated::new(1, handle) };
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::error;
+use std::fmt;
+
+use rkyv::rancor::{Fallible, Source};
+use rkyv::{Archive, Place, Serialize};
+
+use super::SendCell;
+
+/// Archives the wrapped value.
+///
+/// The archived representation is the one of the wrapped value, so archived values can be
+/// deserialized as `T` and wrapped into a new `SendCell` afterwards if needed.
+///
+/// # Panics
+///
+/// Resolving panics if called from a different thread than the one where the original value was
+/// created. This can't happen after successful serialization.
+impl<T: Archive> Archive for SendCell<T> {
+    type Archived = T::Archived;
+    type Resolver = T::Resolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        self.get().resolve(resolver, out)
+    }
+}
+
+/// Serializes the wrapped value.
+///
+/// If called from a different thread than the one where the original value was created, a
+/// serialization error is returned instead of panicking.
+impl<T, S> Serialize<S> for SendCell<T>
+where
+    T: Serialize<S>,
+    S: Fallible + ?Sized,
+    S::Error: Source,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        match self.try_get() {
+            Some(value) => value.serialize(serializer),
+            None => Err(S::Error::new(BoundToAnotherThread)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BoundToAnotherThread;
+
+impl fmt::Display for BoundToAnotherThread {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("value bound to another thread")
+    }
+}
+
+impl error::Error for BoundToAnotherThread {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rkyv::rancor;
+    use std::mem;
+    use std::thread;
+
+    #[test]
+    fn serialize_success() {
+        let cell = SendCell::new(vec![1u32, 2, 3]);
+        let bytes = rkyv::to_bytes::<rancor::Error>(&cell).unwrap();
+        let archived = rkyv::access::<rkyv::Archived<Vec<u32>>, rancor::Error>(&bytes).unwrap();
+        assert_eq!(archived.as_slice(), &[1, 2, 3]);
+
+        let value: Vec<u32> = rkyv::deserialize::<_, rancor::Error>(archived).unwrap();
+        assert_eq!(value, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn serialize_failure() {
+        let t = thread::spawn(move || SendCell::new(vec![1u32, 2, 3]));
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        let err = rkyv::to_bytes::<rancor::Error>(&cell).unwrap_err();
+        assert_eq!(err.to_string(), "value bound to another thread");
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+}
@@ -0,0 +1,211 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops;
+use std::panic;
+use std::ptr::NonNull;
+use std::thread;
+
+use super::{thread_token, AccessError, SendCellViolation, ThreadToken, ViolationKind};
+
+/// Creates a scope for spawning scoped threads together with a place for thread-bound values.
+///
+/// This works like `std::thread::scope()` but additionally passes a `ScopedCells` to `f`. Values
+/// bound with `ScopedCells::bind()` can be used from the calling thread via `Send` handles that
+/// can be passed to the scoped threads.
+///
+/// The values are owned by the scope itself instead of the handles and are all dropped on the
+/// calling thread after all scoped threads are joined. Unlike with `SendCell`, dropping handles
+/// on other threads is therefore not a problem.
+///
+/// ```
+/// let sum = send_cell::scope(|s, cells| {
+///     let cell = cells.bind(std::rc::Rc::new(1));
+///     s.spawn(move || {
+///         assert!(cell.try_get().is_none());
+///     });
+///     **cell + 1
+/// });
+/// assert_eq!(sum, 2);
+/// ```
+pub fn scope<'env, F, R>(f: F) -> R
+where
+    F: for<'scope> FnOnce(&'scope thread::Scope<'scope, 'env>, &'scope ScopedCells<'env>) -> R,
+{
+    let cells = ScopedCells {
+        values: RefCell::new(Vec::new()),
+    };
+    let cells_ptr = &cells as *const ScopedCells<'env>;
+
+    thread::scope(|s| {
+        // Safety: `cells` is only dropped after all scoped threads are joined and `thread::scope()`
+        // returned, and the reference can't escape from `f` because of its higher-ranked lifetime.
+        let cells = unsafe { &*cells_ptr };
+        f(s, cells)
+    })
+}
+
+/// Owner of the thread-bound values of a `scope()`
+///
+/// This is only available on the thread that created the scope and can't be shared with the
+/// scoped threads.
+pub struct ScopedCells<'env> {
+    values: RefCell<Vec<Box<dyn Erased + 'env>>>,
+}
+
+trait Erased {}
+impl<T> Erased for T {}
+
+impl<'env> ScopedCells<'env> {
+    /// Moves `value` into the scope and returns a handle to it.
+    ///
+    /// The value is dropped on the current thread once the scope ends.
+    #[track_caller]
+    pub fn bind<'scope, T: 'env>(&'scope self, value: T) -> ScopedSendCell<'scope, T> {
+        let mut values = self.values.borrow_mut();
+        values.push(Box::new(value));
+        let ptr = NonNull::from(&**values.last().unwrap()).cast::<T>();
+
+        ScopedSendCell {
+            value: ptr,
+            thread_id: ThreadToken::current().thread_id(),
+            location: panic::Location::caller(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'env> fmt::Debug for ScopedCells<'env> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("ScopedCells")
+            .field("len", &self.values.borrow().len())
+            .finish()
+    }
+}
+
+/// A handle to a value owned by a `ScopedCells`
+///
+/// The handle can be copied and transferred to other threads, but like with `SendCell` the value
+/// can only be accessed from the thread on which the scope was created.
+pub struct ScopedSendCell<'scope, T> {
+    value: NonNull<T>,
+    thread_id: thread::ThreadId,
+    location: &'static panic::Location<'static>,
+    phantom: PhantomData<&'scope T>,
+}
+
+impl<'scope, T> ScopedSendCell<'scope, T> {
+    /// Immutably borrows the value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the scope was created.
+    pub fn get(&self) -> &'scope T {
+        self.try_get().unwrap_or_else(|| {
+            panic::panic_any(SendCellViolation::from_error(
+                AccessError::for_value(self.thread_id, self.location),
+                ViolationKind::Access,
+                None,
+            ))
+        })
+    }
+
+    /// Tries to immutably borrow the value.
+    ///
+    /// `None` is returned if called from a different thread than the one where the scope was
+    /// created.
+    pub fn try_get(&self) -> Option<&'scope T> {
+        if thread_token::current_thread_id() == self.thread_id {
+            // Safety: The value is kept alive by the `ScopedCells` for 'scope and is only
+            // accessed from its thread
+            Some(unsafe { &*self.value.as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the `ThreadToken` of the thread this handle is bound to.
+    ///
+    /// This can be called from any thread.
+    pub fn thread_token(&self) -> ThreadToken {
+        ThreadToken::from_thread_id(self.thread_id)
+    }
+}
+
+impl<'scope, T> Clone for ScopedSendCell<'scope, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'scope, T> Copy for ScopedSendCell<'scope, T> {}
+
+impl<'scope, T> ops::Deref for ScopedSendCell<'scope, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<'scope, T: fmt::Debug> fmt::Debug for ScopedSendCell<'scope, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.try_get() {
+            Some(value) => value.fmt(f),
+            None => write!(f, "ScopedSendCell(<bound to {:?}>)", self.thread_id),
+        }
+    }
+}
+
+unsafe impl<'scope, T> Send for ScopedSendCell<'scope, T> {}
+unsafe impl<'scope, T> Sync for ScopedSendCell<'scope, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn bind_success() {
+        let value = scope(|_, cells| {
+            let cell = cells.bind(Rc::new(1));
+            let other = cells.bind(String::from("a"));
+            assert_eq!(**cell, 1);
+            assert_eq!(other.try_get().map(|s| s.as_str()), Some("a"));
+            **cell
+        });
+        assert_eq!(value, 1);
+    }
+
+    #[test]
+    fn handle_on_other_thread() {
+        let dropped_on = RefCell::new(None);
+
+        struct RecordDrop<'a>(&'a RefCell<Option<thread::ThreadId>>);
+
+        impl<'a> Drop for RecordDrop<'a> {
+            fn drop(&mut self) {
+                *self.0.borrow_mut() = Some(thread::current().id());
+            }
+        }
+
+        scope(|s, cells| {
+            let cell = cells.bind(RecordDrop(&dropped_on));
+            s.spawn(move || {
+                assert!(cell.try_get().is_none());
+                let err = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                    cell.get();
+                }))
+                .unwrap_err();
+                assert!(err.downcast_ref::<SendCellViolation>().is_some());
+                // Handle is dropped here on the other thread
+            });
+        });
+
+        assert_eq!(*dropped_on.borrow(), Some(thread::current().id()));
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::borrow::Borrow;
+use std::collections::hash_map::{self, HashMap};
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+use super::{SendCell, ThreadToken};
+
+/// A map of values that are all bound to the same thread
+///
+/// The keys are usually small `Send + Copy` handles like subscription ids. They can be passed to
+/// other threads, which can request the removal of the corresponding values with a
+/// `SendMapRemover`. Requested removals are applied on the thread of the values the next time the
+/// map is modified or when calling `SendMap::apply_removals()`.
+///
+/// For any other access from other threads, the map can be wrapped into a `SendProxy`.
+///
+/// ```
+/// use send_cell::SendMap;
+/// use std::rc::Rc;
+///
+/// let mut callbacks = SendMap::new();
+/// callbacks.insert(1, Rc::new(|| println!("called")));
+/// let remover = callbacks.remover();
+///
+/// std::thread::spawn(move || remover.remove(1)).join().unwrap();
+/// callbacks.apply_removals();
+/// assert!(callbacks.is_empty());
+/// ```
+///
+/// # Warning
+///
+/// Calling `drop` on a `SendMap` or otherwise freeing the values from a different thread than the
+/// one where it was created results in a panic, same as with `SendCell`.
+pub struct SendMap<K, V> {
+    inner: SendCell<HashMap<K, V>>,
+    removals: Arc<Mutex<Vec<K>>>,
+}
+
+impl<K: Eq + Hash, V> SendMap<K, V> {
+    /// Creates a new, empty `SendMap` that is bound to the current thread.
+    #[track_caller]
+    pub fn new() -> Self {
+        SendMap {
+            inner: SendCell::new(HashMap::new()),
+            removals: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    // Applies requested removals and returns the map
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn map_mut(&mut self) -> &mut HashMap<K, V> {
+        self.inner.assert_thread();
+
+        let removals = {
+            let mut removals = self.removals.lock().unwrap_or_else(|err| err.into_inner());
+            if removals.is_empty() {
+                return &mut self.inner.value;
+            }
+            removals.drain(..).collect::<Vec<_>>()
+        };
+
+        for key in removals {
+            self.inner.value.remove(&key);
+        }
+        &mut self.inner.value
+    }
+
+    /// Returns the number of values.
+    ///
+    /// Requested removals that were not applied yet are included. This can be called from any
+    /// thread.
+    pub fn len(&self) -> usize {
+        self.inner.value.len()
+    }
+
+    /// Returns `true` if there are no values.
+    ///
+    /// This can be called from any thread.
+    pub fn is_empty(&self) -> bool {
+        self.inner.value.is_empty()
+    }
+
+    /// Returns the `ThreadToken` of the thread the values are bound to.
+    ///
+    /// This can be called from any thread.
+    pub fn thread_token(&self) -> ThreadToken {
+        self.inner.thread_token()
+    }
+
+    /// Returns a `SendMapRemover` for requesting removals from any thread.
+    pub fn remover(&self) -> SendMapRemover<K> {
+        SendMapRemover {
+            removals: self.removals.clone(),
+        }
+    }
+
+    /// Applies all removals requested via `SendMapRemover`s.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendMap` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn apply_removals(&mut self) {
+        self.map_mut();
+    }
+
+    /// Inserts `value` for `key`, returning the previous value for `key` if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendMap` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map_mut().insert(key, value)
+    }
+
+    /// Removes the value for `key` and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendMap` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.map_mut().remove(key)
+    }
+
+    /// Immutably borrows the value for `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendMap` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.inner.get().get(key)
+    }
+
+    /// Mutably borrows the value for `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendMap` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.map_mut().get_mut(key)
+    }
+
+    /// Returns an iterator over all keys and values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendMap` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn iter(&self) -> hash_map::Iter<'_, K, V> {
+        self.inner.get().iter()
+    }
+
+    /// Tries to immutably borrow the underlying map.
+    ///
+    /// `None` is returned if called from a different thread than the one where the `SendMap` was
+    /// created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_as_map(&self) -> Option<&HashMap<K, V>> {
+        self.inner.try_get()
+    }
+}
+
+impl<K: Eq + Hash, V> Default for SendMap<K, V> {
+    #[track_caller]
+    fn default() -> Self {
+        SendMap::new()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for SendMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.inner.try_get() {
+            Some(map) => map.fmt(f),
+            None => write!(f, "SendMap(<bound to {:?}>)", self.inner.thread_id),
+        }
+    }
+}
+
+/// A handle for requesting the removal of values from a `SendMap` from any thread
+///
+/// This is created by `SendMap::remover()`.
+pub struct SendMapRemover<K> {
+    removals: Arc<Mutex<Vec<K>>>,
+}
+
+impl<K> SendMapRemover<K> {
+    /// Requests the removal of the value for `key`.
+    ///
+    /// The value is removed and dropped on the thread of the `SendMap` the next time it is
+    /// modified.
+    pub fn remove(&self, key: K) {
+        self.removals
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(key);
+    }
+}
+
+impl<K> Clone for SendMapRemover<K> {
+    fn clone(&self) -> Self {
+        SendMapRemover {
+            removals: self.removals.clone(),
+        }
+    }
+}
+
+impl<K> fmt::Debug for SendMapRemover<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("SendMapRemover")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn insert_get_remove() {
+        let mut map = SendMap::new();
+        assert_eq!(map.insert(1, Rc::new("a")), None);
+        assert_eq!(map.insert(2, Rc::new("b")), None);
+        assert_eq!(map.get(&1).map(|v| **v), Some("a"));
+        *map.get_mut(&2).unwrap() = Rc::new("c");
+        assert_eq!(map.remove(&2).map(|v| *v), Some("c"));
+        assert_eq!(map.iter().count(), 1);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn remove_from_other_thread() {
+        let mut map = SendMap::new();
+        map.insert(1, Rc::new(1));
+        map.insert(2, Rc::new(2));
+        let remover = map.remover();
+
+        let map = thread::spawn(move || {
+            assert!(map.try_as_map().is_none());
+            remover.remove(1);
+            map
+        })
+        .join()
+        .unwrap();
+
+        let mut map = map;
+        assert_eq!(map.len(), 2);
+        map.insert(3, Rc::new(3));
+        assert_eq!(map.len(), 2);
+        assert!(map.get(&1).is_none());
+    }
+}
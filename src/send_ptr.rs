@@ -0,0 +1,329 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::panic;
+use std::ptr::NonNull;
+use std::thread;
+
+use super::{thread_token, AccessError, SendCellViolation, ThreadToken, ViolationKind};
+
+fn incorrect_thread(thread_id: thread::ThreadId, location: &'static panic::Location<'static>) -> ! {
+    panic::panic_any(
+        SendCellViolation::from_error(
+            AccessError::for_value(thread_id, location),
+            ViolationKind::Access,
+            None,
+        )
+        .with_description("trying to access wrapped pointer from incorrect thread."),
+    )
+}
+
+/// A raw pointer that may only be dereferenced on the thread where it was wrapped
+///
+/// The pointer itself can be copied and passed to other threads, but retrieving it for
+/// dereferencing via `SendPtr::get()`, `SendPtr::as_ref()` or `SendPtr::as_mut()` checks that
+/// this happens on the thread the pointer is bound to.
+///
+/// ```
+/// use send_cell::SendPtr;
+///
+/// let mut value = 1;
+/// let ptr = SendPtr::new(&mut value as *mut i32);
+/// assert_eq!(unsafe { ptr.as_ref() }, Some(&1));
+///
+/// std::thread::spawn(move || {
+///     assert!(ptr.try_get().is_none());
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+pub struct SendPtr<T: ?Sized> {
+    ptr: *mut T,
+    thread_id: thread::ThreadId,
+    location: &'static panic::Location<'static>,
+}
+
+impl<T: ?Sized> SendPtr<T> {
+    /// Creates a new `SendPtr` for `ptr` that is bound to the current thread.
+    #[track_caller]
+    pub fn new(ptr: *mut T) -> Self {
+        SendPtr {
+            ptr,
+            thread_id: ThreadToken::current().thread_id(),
+            location: panic::Location::caller(),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        thread_token::current_thread_id() == self.thread_id
+    }
+
+    /// Returns the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the pointer was wrapped.
+    pub fn get(&self) -> *mut T {
+        self.try_get()
+            .unwrap_or_else(|| incorrect_thread(self.thread_id, self.location))
+    }
+
+    /// Tries to return the pointer.
+    ///
+    /// `None` is returned if called from a different thread than the one where the pointer was
+    /// wrapped.
+    pub fn try_get(&self) -> Option<*mut T> {
+        if self.is_valid() {
+            Some(self.ptr)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the pointer without checking the thread.
+    ///
+    /// This can be called from any thread, e.g. for using the address as identity. The pointer
+    /// must not be dereferenced from a different thread than the one where it was wrapped.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+
+    /// Returns `true` if the pointer is null.
+    ///
+    /// This can be called from any thread.
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    /// Returns a shared reference to the pointee, or `None` if the pointer is null.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the pointer was wrapped.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements as for `<*mut T>::as_ref()` apply.
+    pub unsafe fn as_ref<'a>(&self) -> Option<&'a T> {
+        self.get().as_ref()
+    }
+
+    /// Returns a mutable reference to the pointee, or `None` if the pointer is null.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the pointer was wrapped.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements as for `<*mut T>::as_mut()` apply.
+    pub unsafe fn as_mut<'a>(&self) -> Option<&'a mut T> {
+        self.get().as_mut()
+    }
+
+    /// Returns the `ThreadToken` of the thread this pointer is bound to.
+    ///
+    /// This can be called from any thread.
+    pub fn thread_token(&self) -> ThreadToken {
+        ThreadToken::from_thread_id(self.thread_id)
+    }
+}
+
+impl<T: ?Sized> Clone for SendPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for SendPtr<T> {}
+
+impl<T: ?Sized> fmt::Debug for SendPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("SendPtr")
+            .field("ptr", &self.ptr)
+            .field("thread", &self.thread_id)
+            .finish()
+    }
+}
+
+impl<T: ?Sized> fmt::Pointer for SendPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Pointer::fmt(&self.ptr, f)
+    }
+}
+
+unsafe impl<T: ?Sized> Send for SendPtr<T> {}
+unsafe impl<T: ?Sized> Sync for SendPtr<T> {}
+
+/// A non-null raw pointer that may only be dereferenced on the thread where it was wrapped
+///
+/// This is the non-null variant of `SendPtr`.
+pub struct SendNonNull<T: ?Sized> {
+    ptr: NonNull<T>,
+    thread_id: thread::ThreadId,
+    location: &'static panic::Location<'static>,
+}
+
+impl<T: ?Sized> SendNonNull<T> {
+    /// Creates a new `SendNonNull` for `ptr` that is bound to the current thread.
+    #[track_caller]
+    pub fn new(ptr: NonNull<T>) -> Self {
+        SendNonNull {
+            ptr,
+            thread_id: ThreadToken::current().thread_id(),
+            location: panic::Location::caller(),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        thread_token::current_thread_id() == self.thread_id
+    }
+
+    /// Returns the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the pointer was wrapped.
+    pub fn get(&self) -> NonNull<T> {
+        self.try_get()
+            .unwrap_or_else(|| incorrect_thread(self.thread_id, self.location))
+    }
+
+    /// Tries to return the pointer.
+    ///
+    /// `None` is returned if called from a different thread than the one where the pointer was
+    /// wrapped.
+    pub fn try_get(&self) -> Option<NonNull<T>> {
+        if self.is_valid() {
+            Some(self.ptr)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the pointer without checking the thread.
+    ///
+    /// This can be called from any thread, e.g. for using the address as identity. The pointer
+    /// must not be dereferenced from a different thread than the one where it was wrapped.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    /// Returns a shared reference to the pointee.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the pointer was wrapped.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements as for `NonNull::as_ref()` apply.
+    pub unsafe fn as_ref<'a>(&self) -> &'a T {
+        &*self.get().as_ptr()
+    }
+
+    /// Returns a mutable reference to the pointee.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the pointer was wrapped.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements as for `NonNull::as_mut()` apply.
+    pub unsafe fn as_mut<'a>(&self) -> &'a mut T {
+        &mut *self.get().as_ptr()
+    }
+
+    /// Returns the `ThreadToken` of the thread this pointer is bound to.
+    ///
+    /// This can be called from any thread.
+    pub fn thread_token(&self) -> ThreadToken {
+        ThreadToken::from_thread_id(self.thread_id)
+    }
+}
+
+impl<T: ?Sized> Clone for SendNonNull<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for SendNonNull<T> {}
+
+impl<T: ?Sized> fmt::Debug for SendNonNull<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("SendNonNull")
+            .field("ptr", &self.ptr)
+            .field("thread", &self.thread_id)
+            .finish()
+    }
+}
+
+impl<T: ?Sized> fmt::Pointer for SendNonNull<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Pointer::fmt(&self.ptr, f)
+    }
+}
+
+impl<T: ?Sized> From<SendNonNull<T>> for SendPtr<T> {
+    fn from(ptr: SendNonNull<T>) -> Self {
+        SendPtr {
+            ptr: ptr.ptr.as_ptr(),
+            thread_id: ptr.thread_id,
+            location: ptr.location,
+        }
+    }
+}
+
+unsafe impl<T: ?Sized> Send for SendNonNull<T> {}
+unsafe impl<T: ?Sized> Sync for SendNonNull<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn send_ptr() {
+        let mut value = 1;
+        let p = SendPtr::new(&mut value as *mut i32);
+        unsafe {
+            *p.as_mut().unwrap() = 2;
+            assert_eq!(p.as_ref(), Some(&2));
+        }
+        assert!(!p.is_null());
+        assert!(SendPtr::new(ptr::null_mut::<i32>()).is_null());
+
+        let r = thread::spawn(move || {
+            assert!(p.try_get().is_none());
+            assert!(!p.as_ptr().is_null());
+            unsafe { p.as_ref().is_some() }
+        })
+        .join()
+        .unwrap_err();
+        let violation = r.downcast_ref::<SendCellViolation>().unwrap();
+        assert_eq!(
+            violation.to_string(),
+            "trying to access wrapped pointer from incorrect thread."
+        );
+    }
+
+    #[test]
+    fn send_non_null() {
+        let mut value = String::from("a");
+        let p = SendNonNull::new(NonNull::from(&mut value));
+        unsafe {
+            p.as_mut().push('b');
+            assert_eq!(p.as_ref(), "ab");
+        }
+
+        let other = SendPtr::from(p);
+        assert_eq!(other.thread_token(), p.thread_token());
+        thread::spawn(move || assert!(p.try_get().is_none()))
+            .join()
+            .unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::iter::FromIterator;
+use std::mem;
+use std::ops;
+use std::slice;
+use std::vec;
+
+use super::{ExecutorDropped, HomeHandle, SendCell, ThreadToken};
+
+/// A vector of values that are all bound to the same thread
+///
+/// This behaves like `Vec<SendCell<T>>` but stores the thread the values are bound to only once
+/// and checks it only once per operation, e.g. once for iterating over all values.
+///
+/// ```
+/// use send_cell::SendVec;
+/// use std::rc::Rc;
+///
+/// let mut values = SendVec::new();
+/// values.push(Rc::new(1));
+/// values.push(Rc::new(2));
+/// values.retain(|value| **value > 1);
+/// assert_eq!(values.iter().map(|value| **value).collect::<Vec<_>>(), vec![2]);
+/// ```
+///
+/// # Warning
+///
+/// Calling `drop` on a `SendVec` or otherwise freeing the values from a different thread than the
+/// one where it was created results in a panic, same as with `SendCell`. Use
+/// `SendVec::drop_on_home()` to drop the values on their thread from anywhere.
+pub struct SendVec<T> {
+    inner: SendCell<Vec<T>>,
+}
+
+impl<T> SendVec<T> {
+    /// Creates a new, empty `SendVec` that is bound to the current thread.
+    #[track_caller]
+    pub fn new() -> Self {
+        SendVec {
+            inner: SendCell::new(Vec::new()),
+        }
+    }
+
+    /// Creates a new, empty `SendVec` with space for at least `capacity` values that is bound to
+    /// the current thread.
+    #[track_caller]
+    pub fn with_capacity(capacity: usize) -> Self {
+        SendVec {
+            inner: SendCell::new(Vec::with_capacity(capacity)),
+        }
+    }
+
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    fn vec_mut(&mut self) -> &mut Vec<T> {
+        self.inner.assert_thread();
+        &mut self.inner.value
+    }
+
+    /// Returns the number of values.
+    ///
+    /// This can be called from any thread.
+    pub fn len(&self) -> usize {
+        self.inner.value.len()
+    }
+
+    /// Returns `true` if there are no values.
+    ///
+    /// This can be called from any thread.
+    pub fn is_empty(&self) -> bool {
+        self.inner.value.is_empty()
+    }
+
+    /// Returns the `ThreadToken` of the thread the values are bound to.
+    ///
+    /// This can be called from any thread.
+    pub fn thread_token(&self) -> ThreadToken {
+        self.inner.thread_token()
+    }
+
+    /// Returns all values as a slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendVec` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn as_slice(&self) -> &[T] {
+        self.inner.get()
+    }
+
+    /// Tries to return all values as a slice.
+    ///
+    /// `None` is returned if called from a different thread than the one where the `SendVec` was
+    /// created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_as_slice(&self) -> Option<&[T]> {
+        self.inner.try_get().map(|vec| vec.as_slice())
+    }
+
+    /// Returns an iterator over all values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendVec` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    /// Appends `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendVec` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn push(&mut self, value: T) {
+        self.vec_mut().push(value)
+    }
+
+    /// Tries to append `value`.
+    ///
+    /// If called from a different thread than the one where the `SendVec` was created, `value` is
+    /// returned as `Err(value)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        if self.inner.is_valid() {
+            self.inner.value.push(value);
+            Ok(())
+        } else {
+            Err(value)
+        }
+    }
+
+    /// Removes the last value and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendVec` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn pop(&mut self) -> Option<T> {
+        self.vec_mut().pop()
+    }
+
+    /// Removes the values in `range` and returns them as an iterator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendVec` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn drain<R: ops::RangeBounds<usize>>(&mut self, range: R) -> vec::Drain<'_, T> {
+        self.vec_mut().drain(range)
+    }
+
+    /// Retains only the values for which `f` returns `true`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendVec` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, f: F) {
+        self.vec_mut().retain(f)
+    }
+
+    /// Consumes the `SendVec`, returning the values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the `SendVec` was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn into_vec(self) -> Vec<T> {
+        self.inner.into_inner()
+    }
+
+    /// Consumes the `SendVec`, returning the values if successful.
+    ///
+    /// The values are returned if this is called from the same thread as the one where the
+    /// `SendVec` was created, otherwise the `SendVec` is returned as `Err(self)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_into_vec(self) -> Result<Vec<T>, Self> {
+        self.inner
+            .try_into_inner()
+            .map_err(|inner| SendVec { inner })
+    }
+
+    /// Drops the `SendVec` on the thread the values are bound to.
+    ///
+    /// If called from that thread, the values are dropped directly. Otherwise they're dropped the
+    /// next time `handle`'s `HomeExecutor` is pumped. If the `HomeExecutor` was dropped already,
+    /// the values can't be dropped on their thread anymore and are leaked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` does not belong to the thread the values are bound to.
+    pub fn drop_on_home(self, handle: &HomeHandle) -> Result<(), ExecutorDropped>
+    where
+        T: 'static,
+    {
+        if self.thread_token().is_current() {
+            drop(self);
+            return Ok(());
+        }
+
+        assert!(
+            self.thread_token() == handle.thread_token(),
+            "home handle belongs to a different thread than the values"
+        );
+        handle.try_spawn(move || drop(self)).map_err(|job| {
+            mem::forget(job);
+            ExecutorDropped
+        })
+    }
+}
+
+impl<T> Default for SendVec<T> {
+    #[track_caller]
+    fn default() -> Self {
+        SendVec::new()
+    }
+}
+
+impl<T> From<Vec<T>> for SendVec<T> {
+    #[track_caller]
+    fn from(vec: Vec<T>) -> Self {
+        SendVec {
+            inner: SendCell::new(vec),
+        }
+    }
+}
+
+/// Collects into a new `SendVec` that is bound to the current thread.
+impl<T> FromIterator<T> for SendVec<T> {
+    #[track_caller]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        SendVec::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+/// Appends the values of the iterator.
+///
+/// # Panics
+///
+/// Panics if called from a different thread than the one where the `SendVec` was created.
+impl<T> Extend<T> for SendVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.vec_mut().extend(iter)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SendVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.try_as_slice() {
+            Some(values) => f.debug_list().entries(values).finish(),
+            None => write!(f, "SendVec(<bound to {:?}>)", self.inner.thread_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+    use HomeExecutor;
+
+    #[test]
+    fn push_iter_drain() {
+        let mut values = (0..3).map(Rc::new).collect::<SendVec<_>>();
+        values.push(Rc::new(3));
+        values.extend(Some(Rc::new(4)));
+        assert_eq!(values.len(), 5);
+        assert_eq!(values.pop().map(|v| *v), Some(4));
+
+        values.retain(|v| **v % 2 == 1);
+        assert_eq!(values.iter().map(|v| **v).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(values.drain(..1).map(|v| *v).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(format!("{:?}", values), "[3]");
+        assert_eq!(values.into_vec().len(), 1);
+    }
+
+    #[test]
+    fn other_thread() {
+        let mut values = SendVec::from(vec![Rc::new(1)]);
+        let executor = HomeExecutor::new();
+        let handle = executor.handle();
+
+        let t = thread::spawn(move || {
+            assert_eq!(values.len(), 1);
+            assert!(values.try_as_slice().is_none());
+            assert!(values.try_push(Rc::new(2)).is_err());
+            let values = values.try_into_vec().err().unwrap();
+            values.drop_on_home(&handle).unwrap();
+        });
+        t.join().unwrap();
+        assert_eq!(executor.pump(), 1);
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Error, Serialize, Serializer};
+
+use super::SendCell;
+
+/// Serializes the wrapped value.
+///
+/// If called from a different thread than the one where the original value was created, a
+/// serialization error is returned instead of panicking.
+impl<T: Serialize> Serialize for SendCell<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.try_get() {
+            Some(value) => value.serialize(serializer),
+            None => Err(S::Error::custom("value bound to another thread")),
+        }
+    }
+}
+
+/// Deserializes a value into a new `SendCell` that is bound to the current thread.
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for SendCell<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(SendCell::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::*;
+    use std::mem;
+    use std::thread;
+
+    #[test]
+    fn serialize_success() {
+        let cell = SendCell::new(vec![1, 2, 3]);
+        assert_eq!(serde_json::to_string(&cell).unwrap(), "[1,2,3]");
+        let cell: SendCell<Vec<i32>> = serde_json::from_str("[1,2,3]").unwrap();
+        assert_eq!(cell.get(), &vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn serialize_failure() {
+        let t = thread::spawn(move || SendCell::new(vec![1, 2, 3]));
+
+        let r = t.join();
+        let cell = r.unwrap();
+
+        let err = serde_json::to_string(&cell).unwrap_err();
+        assert_eq!(err.to_string(), "value bound to another thread");
+        // Forget so drop() is not run, which would panic
+        mem::forget(cell);
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::fmt;
+use std::sync::{Arc, Weak};
+
+use super::{Dispatcher, OwnedRef, Ref, SendCell};
+
+/// A reference-counted `SendCell`
+///
+/// `SendShared` handles can be cloned and transferred to other threads freely, but like with
+/// `SendCell` the contained value can only be accessed from the thread on which it was created.
+///
+/// # Warning
+///
+/// Dropping the last `SendShared` handle from a different thread than the one where the value was
+/// created results in a panic, same as dropping a `SendCell` from a different thread. This can be
+/// avoided by forwarding the drop to the thread of the value with
+/// `SendShared::new_with_dispatcher()`, or by converting a `SendCell` that was built with
+/// `SendCellBuilder::dispatcher()`. `WeakShared` handles do not keep the value alive and can be
+/// dropped from any thread.
+pub struct SendShared<T> {
+    cell: Arc<SendCell<T>>,
+}
+
+impl<T> SendShared<T> {
+    /// Creates a new `SendShared` containing `value`.
+    #[track_caller]
+    pub fn new(value: T) -> Self {
+        SendShared {
+            cell: Arc::new(SendCell::new(value)),
+        }
+    }
+
+    /// Immutably borrows the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn get(&self) -> &T {
+        self.cell.get()
+    }
+
+    /// Tries to immutably borrow the wrapped value.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_get(&self) -> Option<&T> {
+        self.cell.try_get()
+    }
+
+    /// Immutably borrows the wrapped value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.cell.borrow()
+    }
+
+    /// Tries to immutably borrow the wrapped value.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_borrow(&self) -> Option<Ref<'_, T>> {
+        self.cell.try_borrow()
+    }
+
+    /// Immutably borrows the wrapped value, keeping it alive for as long as the borrow exists.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created.
+    pub fn borrow_owned(&self) -> OwnedRef<T> {
+        OwnedRef::new(self.cell.clone())
+    }
+
+    /// Tries to immutably borrow the wrapped value, keeping it alive for as long as the borrow
+    /// exists.
+    ///
+    /// `None` is returned if called from a different thread than the one where the original value
+    /// was created.
+    pub fn try_borrow_owned(&self) -> Option<OwnedRef<T>> {
+        OwnedRef::try_new(self.cell.clone()).ok()
+    }
+
+    /// Creates a new `WeakShared` handle to the wrapped value.
+    ///
+    /// This can be called from any thread.
+    pub fn downgrade(this: &Self) -> WeakShared<T> {
+        WeakShared {
+            cell: Arc::downgrade(&this.cell),
+        }
+    }
+
+    /// Returns the number of `SendShared` handles to the wrapped value.
+    pub fn strong_count(this: &Self) -> usize {
+        Arc::strong_count(&this.cell)
+    }
+
+    /// Returns `true` if both `SendShared` handles point to the same value.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.cell, &other.cell)
+    }
+
+    /// Returns the wrapped value if this is the only `SendShared` handle and if called from the
+    /// same thread as the one where the original value was created.
+    ///
+    /// Otherwise the `SendShared` is returned as `Err(this)`.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if this.cell.try_get().is_none() {
+            return Err(this);
+        }
+
+        match Arc::try_unwrap(this.cell) {
+            Ok(cell) => Ok(cell.into_inner()),
+            Err(cell) => Err(SendShared { cell }),
+        }
+    }
+}
+
+impl<T: 'static> SendShared<T> {
+    /// Creates a new `SendShared` containing `value` whose last handle can be dropped on any
+    /// thread.
+    ///
+    /// If the last handle is dropped on a different thread than the one where the value was
+    /// created, the value is forwarded to `dispatcher` and dropped on its thread. See
+    /// `SendCellBuilder::dispatcher()` for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dispatcher` belongs to a different thread.
+    #[track_caller]
+    pub fn new_with_dispatcher<D: Dispatcher>(value: T, dispatcher: D) -> Self {
+        SendShared {
+            cell: Arc::new(SendCell::builder().dispatcher(dispatcher).build(value)),
+        }
+    }
+}
+
+impl<T> From<T> for SendShared<T> {
+    #[track_caller]
+    fn from(t: T) -> SendShared<T> {
+        SendShared::new(t)
+    }
+}
+
+impl<T> From<SendCell<T>> for SendShared<T> {
+    fn from(cell: SendCell<T>) -> SendShared<T> {
+        SendShared {
+            cell: Arc::new(cell),
+        }
+    }
+}
+
+impl<T: Default> Default for SendShared<T> {
+    #[track_caller]
+    fn default() -> SendShared<T> {
+        SendShared::new(T::default())
+    }
+}
+
+impl<T> Clone for SendShared<T> {
+    fn clone(&self) -> SendShared<T> {
+        SendShared {
+            cell: self.cell.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SendShared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.cell.fmt(f)
+    }
+}
+
+/// A weak handle to the value of a `SendShared`
+///
+/// `WeakShared` handles do not keep the value alive and can be upgraded to a `SendShared` from
+/// any thread as long as the value is still alive.
+pub struct WeakShared<T> {
+    cell: Weak<SendCell<T>>,
+}
+
+impl<T> WeakShared<T> {
+    /// Creates a new `WeakShared` that is not pointing to any value.
+    pub fn new() -> Self {
+        WeakShared { cell: Weak::new() }
+    }
+
+    /// Tries to upgrade the `WeakShared` to a `SendShared`.
+    ///
+    /// `None` is returned if the value was dropped already.
+    pub fn upgrade(&self) -> Option<SendShared<T>> {
+        self.cell.upgrade().map(|cell| SendShared { cell })
+    }
+}
+
+impl<T> Default for WeakShared<T> {
+    fn default() -> WeakShared<T> {
+        WeakShared::new()
+    }
+}
+
+impl<T> Clone for WeakShared<T> {
+    fn clone(&self) -> WeakShared<T> {
+        WeakShared {
+            cell: self.cell.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for WeakShared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("(WeakShared)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::thread;
+    use SendCellViolation;
+
+    #[test]
+    fn get_success() {
+        let shared = SendShared::new(1);
+        let other = shared.clone();
+        assert_eq!(shared.get(), &1);
+        assert_eq!(other.try_get(), Some(&1));
+        assert_eq!(*other.borrow_owned(), 1);
+        assert!(SendShared::ptr_eq(&shared, &other));
+        assert_eq!(SendShared::strong_count(&shared), 2);
+    }
+
+    #[test]
+    fn try_get_failure() {
+        let t = thread::spawn(move || SendShared::new(1));
+
+        let r = t.join();
+        let shared = r.unwrap();
+
+        assert_eq!(shared.try_get(), None);
+        assert!(shared.try_borrow_owned().is_none());
+        // Forget so drop() is not run, which would panic
+        mem::forget(shared);
+    }
+
+    #[test]
+    fn location() {
+        let shared = SendShared::new(1);
+        let line = line!() - 1;
+        let other = shared.clone();
+        thread::spawn(move || {
+            let err = panic::catch_unwind(AssertUnwindSafe(|| {
+                other.get();
+            }))
+            .unwrap_err();
+            let violation = err.downcast_ref::<SendCellViolation>().unwrap();
+            assert_eq!(violation.location().line(), line);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn clone_from_other_thread() {
+        let shared = SendShared::new(1);
+        let other = shared.clone();
+        let t = thread::spawn(move || {
+            let clone = other.clone();
+            assert_eq!(clone.try_get(), None);
+            clone
+        });
+
+        let other = t.join().unwrap();
+        assert_eq!(other.get(), &1);
+        assert_eq!(SendShared::strong_count(&shared), 2);
+    }
+
+    #[test]
+    fn downgrade_upgrade() {
+        let shared = SendShared::new(1);
+        let weak = SendShared::downgrade(&shared);
+        let t = thread::spawn(move || {
+            let upgraded = weak.upgrade().unwrap();
+            assert_eq!(upgraded.try_get(), None);
+            (upgraded, weak)
+        });
+
+        let (upgraded, weak) = t.join().unwrap();
+        assert_eq!(upgraded.get(), &1);
+        drop(upgraded);
+        drop(shared);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn last_drop_forwarded() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+        use HomeExecutor;
+
+        struct SetOnDrop(Rc<Cell<bool>>);
+
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let executor = HomeExecutor::new();
+        let dropped = Rc::new(Cell::new(false));
+        let shared = SendShared::new_with_dispatcher(SetOnDrop(dropped.clone()), executor.handle());
+        let other = shared.clone();
+        drop(shared);
+
+        thread::spawn(move || drop(other)).join().unwrap();
+        assert!(!dropped.get());
+        executor.pump();
+        assert!(dropped.get());
+    }
+
+    #[test]
+    fn try_unwrap() {
+        let shared = SendShared::new(1);
+        let other = shared.clone();
+        let shared = SendShared::try_unwrap(shared).unwrap_err();
+        drop(other);
+        assert_eq!(SendShared::try_unwrap(shared).ok(), Some(1));
+    }
+}
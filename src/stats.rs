@@ -0,0 +1,158 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::any;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread;
+
+type Counts = HashMap<(thread::ThreadId, &'static str), usize>;
+
+fn counts() -> MutexGuard<'static, Counts> {
+    static COUNTS: OnceLock<Mutex<Counts>> = OnceLock::new();
+
+    COUNTS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+// Called whenever a `SendCell<T>` bound to `thread_id` is created
+pub(crate) fn add<T>(thread_id: thread::ThreadId) {
+    *counts()
+        .entry((thread_id, any::type_name::<T>()))
+        .or_insert(0) += 1;
+}
+
+// Called whenever the value of a `SendCell<T>` bound to `thread_id` was dropped or moved out
+pub(crate) fn remove<T>(thread_id: thread::ThreadId) {
+    let mut counts = counts();
+    let key = (thread_id, any::type_name::<T>());
+    if let Some(count) = counts.get_mut(&key) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(&key);
+        }
+    }
+}
+
+/// The number of live `SendCell`s of one type bound to one thread
+///
+/// Returned by `live_cells()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LiveCells {
+    thread: thread::ThreadId,
+    type_name: &'static str,
+    count: usize,
+}
+
+impl LiveCells {
+    /// Returns the `ThreadId` of the thread the cells are bound to.
+    pub fn thread(&self) -> thread::ThreadId {
+        self.thread
+    }
+
+    /// Returns the name of the type wrapped by the cells, as returned by
+    /// `std::any::type_name()`.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns the number of cells.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Returns the number of `SendCell`s whose value was not dropped or moved out yet, grouped by the
+/// thread they are bound to and by the wrapped type.
+///
+/// Cells that were leaked, e.g. because they were dropped on a different thread, stay counted.
+/// Comparing this with the threads that are still alive allows finding values that can never be
+/// cleaned up anymore.
+///
+/// ```
+/// use send_cell::SendCell;
+///
+/// let cell = SendCell::new(1u8);
+/// let current = std::thread::current().id();
+/// assert!(send_cell::live_cells()
+///     .iter()
+///     .any(|cells| cells.thread() == current && cells.type_name() == "u8"));
+/// # drop(cell);
+/// ```
+pub fn live_cells() -> Vec<LiveCells> {
+    let mut live = counts()
+        .iter()
+        .map(|(&(thread, type_name), &count)| LiveCells {
+            thread,
+            type_name,
+            count,
+        })
+        .collect::<Vec<_>>();
+    live.sort_by(|a, b| {
+        format!("{:?}", a.thread)
+            .cmp(&format!("{:?}", b.thread))
+            .then(a.type_name.cmp(b.type_name))
+    });
+    live
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+    use std::rc::Rc;
+    use {DropPolicy, HomeExecutor, SendCell};
+
+    fn count_of<T>() -> usize {
+        let current = thread::current().id();
+        live_cells()
+            .iter()
+            .find(|cells| cells.thread() == current && cells.type_name() == any::type_name::<T>())
+            .map_or(0, |cells| cells.count())
+    }
+
+    #[test]
+    fn counts() {
+        struct Marker(#[allow(dead_code)] Rc<()>);
+
+        assert_eq!(count_of::<Marker>(), 0);
+        let a = SendCell::new(Marker(Rc::new(())));
+        let b = SendCell::new(Marker(Rc::new(())));
+        assert_eq!(count_of::<Marker>(), 2);
+
+        drop(a);
+        assert_eq!(count_of::<Marker>(), 1);
+        let value = b.into_inner();
+        assert_eq!(count_of::<Marker>(), 0);
+
+        let c = SendCell::new(value);
+        mem::forget(c);
+        assert_eq!(count_of::<Marker>(), 1);
+    }
+
+    #[test]
+    fn forwarded_drops() {
+        struct Marker(#[allow(dead_code)] Rc<()>);
+
+        let executor = HomeExecutor::new();
+        let cell = SendCell::builder()
+            .dispatcher(executor.handle())
+            .build(Marker(Rc::new(())));
+        thread::spawn(move || drop(cell)).join().unwrap();
+        assert_eq!(count_of::<Marker>(), 1);
+        assert_eq!(executor.pump(), 1);
+        assert_eq!(count_of::<Marker>(), 0);
+
+        // Without executor the value is leaked and stays counted once
+        let cell = SendCell::builder()
+            .drop_policy(DropPolicy::Leak)
+            .dispatcher(executor.handle())
+            .build(Marker(Rc::new(())));
+        drop(executor);
+        thread::spawn(move || drop(cell)).join().unwrap();
+        assert_eq!(count_of::<Marker>(), 1);
+    }
+}
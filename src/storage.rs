@@ -0,0 +1,146 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::mem::ManuallyDrop;
+use std::ops;
+use std::ptr;
+
+use fragile::Fragile;
+
+// Storage of the value of a `SendCell`
+//
+// Values are stored inline, except for a `fragile::Fragile` that is converted into a `SendCell` on
+// a different thread than the one it is bound to. Such a `Fragile` is kept as is and only accessed
+// through its own API, so that it can be handed out again by `SendCell::into_fragile()` from any
+// thread. None of the accessors check the thread, that's up to the `SendCell`.
+pub(crate) enum Storage<T> {
+    // A `Fragile` converted into a `SendCell` on a different thread than the one it is bound to.
+    // Its value and thread can only be reached through `Fragile` itself on that thread.
+    Foreign(ManuallyDrop<Fragile<T>>),
+    Inline(ManuallyDrop<T>),
+    // The value was moved out by `SendCell::update()` and not written back because of a panic
+    Lost,
+}
+
+impl<T> Storage<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Storage::Inline(ManuallyDrop::new(value))
+    }
+
+    // Stores `fragile` for a `SendCell`, which is bound to the same thread. On that thread the
+    // value is moved out of the `Fragile`.
+    pub(crate) fn from_fragile(fragile: Fragile<T>) -> Self {
+        match fragile.try_into_inner() {
+            Ok(value) => Storage::new(value),
+            Err(fragile) => Storage::Foreign(ManuallyDrop::new(fragile)),
+        }
+    }
+
+    pub(crate) fn is_lost(&self) -> bool {
+        matches!(*self, Storage::Lost)
+    }
+
+    // Returns whether the current thread is the one of a `Foreign` storage, or `None` for all
+    // other storage whose thread is tracked by the `SendCell`
+    pub(crate) fn is_valid_foreign(&self) -> Option<bool> {
+        match *self {
+            Storage::Foreign(ref fragile) => Some(fragile.is_valid()),
+            _ => None,
+        }
+    }
+
+    // Returns a pointer to the value. This is null for `Foreign` storage on any other thread than
+    // the one it is bound to, and for `Lost` storage.
+    pub(crate) fn as_ptr(&self) -> *const T {
+        match *self {
+            Storage::Foreign(ref fragile) => match fragile.try_get() {
+                Ok(value) => value,
+                Err(_) => ptr::null(),
+            },
+            Storage::Inline(ref value) => &**value,
+            Storage::Lost => ptr::null(),
+        }
+    }
+
+    // Returns a mutable pointer to the value. This is null for `Foreign` storage on any other
+    // thread than the one it is bound to, and for `Lost` storage.
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut T {
+        match *self {
+            Storage::Foreign(ref mut fragile) => match fragile.try_get_mut() {
+                Ok(value) => value,
+                Err(_) => ptr::null_mut(),
+            },
+            Storage::Inline(ref mut value) => &mut **value,
+            Storage::Lost => ptr::null_mut(),
+        }
+    }
+
+    // Moves the value out of the storage. Panics for `Lost` storage, and for `Foreign` storage on
+    // any other thread than the one it is bound to.
+    //
+    // Safety: The storage must not be used anymore afterwards
+    pub(crate) unsafe fn take(&mut self) -> T {
+        match *self {
+            Storage::Foreign(ref mut fragile) => ManuallyDrop::take(fragile).into_inner(),
+            Storage::Inline(ref mut value) => ManuallyDrop::take(value),
+            Storage::Lost => lost(),
+        }
+    }
+
+    // Drops the value, if it was not lost.
+    //
+    // Safety: Same as for `take()`
+    pub(crate) unsafe fn drop_in_place(&mut self) {
+        if !self.is_lost() {
+            drop(self.take());
+        }
+    }
+
+    // Replaces the value with `value` without dropping the old one.
+    //
+    // Safety: The old value must have been moved out with `take()` before. `Foreign` storage must
+    // only be written from the thread it is bound to.
+    pub(crate) unsafe fn restore(&mut self, value: T) {
+        *self = Storage::new(value);
+    }
+
+    // Moves the value out as a `Fragile`, or returns `None` if the storage does not contain a
+    // `Fragile`.
+    //
+    // Safety: The storage must not be used anymore afterwards if `Some` is returned
+    pub(crate) unsafe fn take_fragile(&mut self) -> Option<Fragile<T>> {
+        match *self {
+            Storage::Foreign(ref mut fragile) => Some(ManuallyDrop::take(fragile)),
+            Storage::Inline(_) | Storage::Lost => None,
+        }
+    }
+}
+
+fn lost() -> ! {
+    panic!("value was lost because of a panic during an earlier update");
+}
+
+// Accessing the value is only allowed on the thread it is bound to, which has to be checked by the
+// caller. `Foreign` storage panics on any other thread, `Lost` storage always panics.
+impl<T> ops::Deref for Storage<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match *self {
+            Storage::Foreign(ref fragile) => fragile.get(),
+            Storage::Inline(ref value) => value,
+            Storage::Lost => lost(),
+        }
+    }
+}
+
+impl<T> ops::DerefMut for Storage<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        match *self {
+            Storage::Foreign(ref mut fragile) => fragile.get_mut(),
+            Storage::Inline(ref mut value) => value,
+            Storage::Lost => lost(),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use thread_local::ThreadLocal;
+
+use super::SendCell;
+
+impl<T> SendCell<T> {
+    /// Moves the `SendCell` into the slot of the current thread in `tl` and returns a reference to
+    /// the wrapped value.
+    ///
+    /// A `ThreadLocal<SendCell<T>>` holds one value per thread for types that do not implement
+    /// `Send`, with each value bound to the thread of its slot. `ThreadLocal::into_iter()` returns
+    /// the cells of all threads, which can then be sent back to their threads like any other
+    /// `SendCell`.
+    ///
+    /// # Warning
+    ///
+    /// Dropping a `ThreadLocal<SendCell<T>>` drops all cells on the current thread, which results
+    /// in a panic if any of them belongs to another thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from a different thread than the one where the original value was created,
+    /// or if the slot of the current thread in `tl` is already occupied.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn into_thread_local(self, tl: &ThreadLocal<SendCell<T>>) -> &T {
+        self.assert_thread();
+        self.insert_into_thread_local(tl)
+            .unwrap_or_else(|_| panic!("thread-local slot of the current thread already occupied."))
+    }
+
+    /// Tries to move the `SendCell` into the slot of the current thread in `tl` and returns a
+    /// reference to the wrapped value.
+    ///
+    /// If called from a different thread than the one where the original value was created, or if
+    /// the slot of the current thread in `tl` is already occupied, the `SendCell` is returned as
+    /// `Err(self)`.
+    #[cfg_attr(feature = "access_hook", track_caller)]
+    pub fn try_into_thread_local(self, tl: &ThreadLocal<SendCell<T>>) -> Result<&T, Self> {
+        if !self.is_valid() {
+            return Err(self);
+        }
+
+        self.insert_into_thread_local(tl)
+    }
+
+    // Moves the `SendCell` into the empty slot of the current thread in `tl` after its thread was
+    // checked
+    fn insert_into_thread_local(self, tl: &ThreadLocal<SendCell<T>>) -> Result<&T, Self> {
+        let mut cell = Some(self);
+        let slot = tl.get_or(|| cell.take().unwrap());
+        match cell {
+            None => Ok(&slot.value),
+            Some(cell) => Err(cell),
+        }
+    }
+
+    /// Returns a reference to the value in the slot of the current thread in `tl`, creating it
+    /// with `f` first if the slot is empty.
+    pub fn thread_local_get_or<F: FnOnce() -> T>(tl: &ThreadLocal<SendCell<T>>, f: F) -> &T {
+        tl.get_or(|| SendCell::new(f())).get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn into_thread_local() {
+        let tl = ThreadLocal::new();
+        assert_eq!(**SendCell::new(Rc::new(1)).into_thread_local(&tl), 1);
+        let cell = SendCell::new(Rc::new(2));
+        let cell = cell.try_into_thread_local(&tl).unwrap_err();
+        assert_eq!(**cell.get(), 2);
+        assert_eq!(**SendCell::thread_local_get_or(&tl, || Rc::new(3)), 1);
+    }
+
+    #[test]
+    fn thread_local_per_thread() {
+        let tl = ThreadLocal::new();
+        assert_eq!(*SendCell::thread_local_get_or(&tl, || 1), 1);
+
+        let (tl, cell) = thread::spawn(move || {
+            assert_eq!(*SendCell::thread_local_get_or(&tl, || 2), 2);
+            (tl, SendCell::new(3))
+        })
+        .join()
+        .unwrap();
+
+        let cell = cell.try_into_thread_local(&tl).unwrap_err();
+        assert_eq!(tl.into_iter().count(), 2);
+        assert_eq!(cell.try_get(), None);
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::sync::OnceLock;
+use std::thread;
+
+/// Identifies the thread a `SendCell` is bound to
+///
+/// Tokens are cheap to copy and can be sent to and shared with other threads. They can be compared
+/// with each other and with `SendCell`s via `SendCell::is_bound_to()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ThreadToken {
+    id: thread::ThreadId,
+}
+
+impl ThreadToken {
+    /// Returns the `ThreadToken` of the current thread.
+    pub fn current() -> Self {
+        ThreadToken {
+            id: super::orphan::register_current_thread(),
+        }
+    }
+
+    /// Returns `true` if this is the `ThreadToken` of the current thread.
+    pub fn is_current(&self) -> bool {
+        current_thread_id() == self.id
+    }
+
+    /// Returns the `ThreadId` of the thread this token belongs to.
+    pub fn thread_id(&self) -> thread::ThreadId {
+        self.id
+    }
+
+    pub(crate) fn from_thread_id(id: thread::ThreadId) -> Self {
+        ThreadToken { id }
+    }
+}
+
+// Returns the `ThreadId` of the current thread. With the `fast_thread_id` feature it is cached in a
+// thread-local variable, which avoids creating a `Thread` handle on every access.
+//
+// Native thread ids like `pthread_self()` or `gettid()` are deliberately not used. They are reused
+// for new threads once a thread exited, so the values of orphaned `SendCell`s would become
+// accessible from an unrelated thread.
+#[cfg(feature = "fast_thread_id")]
+pub(crate) fn current_thread_id() -> thread::ThreadId {
+    thread_local! {
+        static CURRENT_ID: thread::ThreadId = thread::current().id();
+    }
+
+    CURRENT_ID
+        .try_with(|id| *id)
+        .unwrap_or_else(|_| thread::current().id())
+}
+
+#[cfg(not(feature = "fast_thread_id"))]
+pub(crate) fn current_thread_id() -> thread::ThreadId {
+    thread::current().id()
+}
+
+// Returns a `ThreadId` that does not belong to any running thread, for `SendCell`s whose thread is
+// not known
+pub(crate) fn unknown_thread_id() -> thread::ThreadId {
+    static UNKNOWN: OnceLock<thread::ThreadId> = OnceLock::new();
+    *UNKNOWN.get_or_init(|| thread::spawn(|| thread::current().id()).join().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current() {
+        let token = ThreadToken::current();
+        assert!(token.is_current());
+        assert_eq!(token, ThreadToken::current());
+        assert_eq!(token.thread_id(), thread::current().id());
+
+        let other = thread::spawn(ThreadToken::current).join().unwrap();
+        assert!(!other.is_current());
+        assert_ne!(token, other);
+    }
+
+    #[test]
+    fn current_thread_id() {
+        assert_eq!(super::current_thread_id(), thread::current().id());
+        let (id, other) = thread::spawn(|| (super::current_thread_id(), thread::current().id()))
+            .join()
+            .unwrap();
+        assert_eq!(id, other);
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use super::{thread_token, SendCell};
+
+/// Marker trait for types whose ownership can be moved between threads
+///
+/// Some types do not implement `Send` only for API hygiene, e.g. because they must not be used
+/// from multiple threads at once, but moving them to another thread as a whole is sound. For such
+/// types `SendCell::transfer_to_current_thread()` allows rebinding a `SendCell` to another thread
+/// without `unsafe` code at every call site.
+///
+/// # Safety
+///
+/// Implementing this trait asserts that moving exclusive ownership of a value of this type to
+/// another thread, and using and dropping it there afterwards, is sound.
+pub unsafe trait TransferSafe {}
+
+impl<T: TransferSafe> SendCell<T> {
+    /// Consumes the `SendCell`, returning a new `SendCell` with the same value that is bound to the
+    /// current thread.
+    ///
+    /// This can be called from any thread.
+    pub fn transfer_to_current_thread(mut self) -> SendCell<T> {
+        self.rebind(thread_token::current_thread_id());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::marker::PhantomData;
+    use std::thread;
+
+    struct Handle {
+        id: u32,
+        _not_send: PhantomData<*const ()>,
+    }
+
+    unsafe impl TransferSafe for Handle {}
+
+    #[test]
+    fn transfer_to_current_thread() {
+        let cell = thread::spawn(|| {
+            SendCell::new(Handle {
+                id: 1,
+                _not_send: PhantomData,
+            })
+        })
+        .join()
+        .unwrap();
+        assert!(cell.try_get().is_none());
+
+        let cell = cell.transfer_to_current_thread();
+        assert_eq!(cell.get().id, 1);
+        assert!(cell.thread_token().is_current());
+    }
+}
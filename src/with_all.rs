@@ -0,0 +1,168 @@
+// Copyright (C) 2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the MIT license, see the LICENSE file or <http://opensource.org/licenses/MIT>
+
+use std::error;
+use std::fmt;
+use std::panic;
+
+use super::{AccessError, SendCell, SendCellViolation, ThreadToken, ViolationKind};
+
+/// Tuples of `SendCell` references that can be accessed together with `with_all()`
+///
+/// This is implemented for tuples of up to 8 `&SendCell`.
+pub trait SendCellTuple<'a> {
+    /// The tuple of references to the wrapped values.
+    type Refs;
+
+    /// Borrows all wrapped values if all cells are bound to the thread of `token`, which has to be
+    /// the current thread, and can be accessed like with `SendCell::try_get()`.
+    ///
+    /// Otherwise an error with the index of the first cell that can't be accessed is returned.
+    fn try_get_all(self, token: ThreadToken) -> Result<Self::Refs, ForeignCellError>;
+}
+
+macro_rules! impl_send_cell_tuple {
+    ($($idx:tt $t:ident),+) => {
+        impl<'a, $($t: 'a),+> SendCellTuple<'a> for ($(&'a SendCell<$t>,)+) {
+            type Refs = ($(&'a $t,)+);
+
+            #[cfg_attr(feature = "access_hook", track_caller)]
+            fn try_get_all(self, token: ThreadToken) -> Result<Self::Refs, ForeignCellError> {
+                Ok(($(
+                    match self.$idx.try_get() {
+                        Some(value) if self.$idx.is_bound_to(&token) => value,
+                        _ => {
+                            return Err(ForeignCellError {
+                                index: $idx,
+                                error: AccessError::new(self.$idx),
+                            })
+                        }
+                    },
+                )+))
+            }
+        }
+    };
+}
+
+impl_send_cell_tuple!(0 A);
+impl_send_cell_tuple!(0 A, 1 B);
+impl_send_cell_tuple!(0 A, 1 B, 2 C);
+impl_send_cell_tuple!(0 A, 1 B, 2 C, 3 D);
+impl_send_cell_tuple!(0 A, 1 B, 2 C, 3 D, 4 E);
+impl_send_cell_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+impl_send_cell_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+impl_send_cell_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+
+/// Calls `f` with borrows of the values of all `cells`.
+///
+/// ```
+/// let a = send_cell::SendCell::new(1);
+/// let b = send_cell::SendCell::new(String::from("b"));
+/// let len = send_cell::with_all((&a, &b), |(a, b)| *a + b.len());
+/// assert_eq!(len, 2);
+/// ```
+///
+/// # Panics
+///
+/// Panics if any of the cells is bound to a different thread than the current one or can't be
+/// accessed for another reason, same as `SendCell::get()`.
+#[cfg_attr(feature = "access_hook", track_caller)]
+pub fn with_all<'a, C: SendCellTuple<'a>, R, F: FnOnce(C::Refs) -> R>(cells: C, f: F) -> R {
+    try_with_all(cells, f).unwrap_or_else(|err| {
+        panic::panic_any(SendCellViolation::from_error(
+            err.error,
+            ViolationKind::Access,
+            None,
+        ))
+    })
+}
+
+/// Tries to call `f` with borrows of the values of all `cells`.
+///
+/// If any of the cells is bound to a different thread than the current one or can't be accessed
+/// for another reason, same as with `SendCell::try_get()`, `f` is not called and an error with the
+/// index of the first such cell is returned.
+#[cfg_attr(feature = "access_hook", track_caller)]
+pub fn try_with_all<'a, C: SendCellTuple<'a>, R, F: FnOnce(C::Refs) -> R>(
+    cells: C,
+    f: F,
+) -> Result<R, ForeignCellError> {
+    cells.try_get_all(ThreadToken::current()).map(f)
+}
+
+/// An error returned by `try_with_all()` if a cell can't be accessed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ForeignCellError {
+    index: usize,
+    error: AccessError,
+}
+
+impl ForeignCellError {
+    /// Returns the index of the cell in the tuple that can't be accessed.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the `AccessError` of the cell that can't be accessed.
+    pub fn error(&self) -> &AccessError {
+        &self.error
+    }
+}
+
+impl fmt::Display for ForeignCellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "cell {}: {}", self.index, self.error)
+    }
+}
+
+impl error::Error for ForeignCellError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn with_all_success() {
+        let a = SendCell::new(Rc::new(1));
+        let b = SendCell::new(2);
+        let c = SendCell::new("c");
+        assert_eq!(with_all((&a,), |(a,)| **a), 1);
+        assert_eq!(
+            try_with_all((&a, &b, &c), |(a, b, c)| (**a + *b, *c)),
+            Ok((3, "c"))
+        );
+    }
+
+    #[test]
+    fn with_all_foreign_cell() {
+        let a = SendCell::new(1);
+        let b = thread::spawn(move || SendCell::new(Rc::new(2)))
+            .join()
+            .unwrap();
+
+        let err = try_with_all((&a, &b), |_| unreachable!()).unwrap_err();
+        assert_eq!(err.index(), 1);
+        assert_eq!(err.error().origin_thread(), b.thread_token().thread_id());
+
+        let err = panic::catch_unwind(|| with_all((&a, &b), |_| ())).unwrap_err();
+        let violation = err.downcast_ref::<SendCellViolation>().unwrap();
+        assert_eq!(violation.error(), &AccessError::new(&b));
+        // Forget so drop() is not run, which would panic
+        mem::forget(b);
+    }
+
+    #[test]
+    fn with_all_invalid_cell() {
+        let a = SendCell::new(1);
+        let b = SendCell::new_with_validator(0, |v| *v > 0);
+
+        let err = try_with_all((&a, &b), |_| unreachable!()).unwrap_err();
+        assert_eq!(err.index(), 1);
+        assert_eq!(err.error().origin_thread(), err.error().current_thread());
+        assert!(err.to_string().contains("failed its validity check"));
+    }
+}